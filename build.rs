@@ -1,6 +1,17 @@
+use std::{
+    collections::BTreeMap,
+    env,
+    error::Error,
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+};
+
 fn main() {
     slint_build::compile("ui/emoji-picker.slint").unwrap();
-    if cfg!(target_os = "windows") {
+    generate_annotations();
+    #[cfg(target_os = "windows")]
+    {
         let mut res = winresource::WindowsResource::new();
         res.set_icon("assets/ico.ico");
         res.set_icon_with_id("assets/ico.ico", "tray-icon");
@@ -9,3 +20,125 @@ fn main() {
         }
     }
 }
+
+/// Emoji (without U+FE0F) -> (text-to-speech name, keywords)
+type Annotations = BTreeMap<String, (String, Vec<String>)>;
+
+/// Languages whose CLDR annotations are bundled, unless `$EMOJI_PICKER_LANGUAGES`
+/// (a comma-separated list) says otherwise. English is always bundled, as it is
+/// the fallback.
+const LANGUAGES: &[&str] = &["en", "de", "es", "fr", "it", "pt"];
+
+/// Bundles the CLDR annotations (names and keywords of the emojis) found in
+/// the `cldr-json` submodule, or in `$CLDR_JSON_DIR`, into `$OUT_DIR/annotations.rs`.
+/// If they are missing, the emojis are only searchable by name and shortcodes.
+fn generate_annotations() {
+    println!("cargo:rerun-if-env-changed=CLDR_JSON_DIR");
+    println!("cargo:rerun-if-env-changed=EMOJI_PICKER_LANGUAGES");
+    let cldr_dir = env::var_os("CLDR_JSON_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("emojis/cldr-json/cldr-json"));
+    let mut languages: Vec<String> = match env::var("EMOJI_PICKER_LANGUAGES") {
+        Ok(languages) => languages.split(',').map(|l| l.trim().to_owned()).collect(),
+        Err(_) => LANGUAGES.iter().map(|l| l.to_string()).collect(),
+    };
+    languages.retain(|l| !l.is_empty() && l != "en");
+    languages.insert(0, "en".to_owned());
+
+    if !cldr_dir.exists() {
+        println!(
+            "cargo:warning=Missing CLDR annotations in {}. Did you clone the submodules?",
+            cldr_dir.display()
+        );
+    }
+
+    let mut out = String::new();
+    for language in languages.iter() {
+        let files = [
+            cldr_dir.join(format!(
+                "cldr-annotations-full/annotations/{language}/annotations.json"
+            )),
+            cldr_dir.join(format!(
+                "cldr-annotations-derived-full/annotationsDerived/{language}/annotations.json"
+            )),
+        ];
+
+        let mut annotations = Annotations::new();
+        for file in files.iter().filter(|file| file.exists()) {
+            println!("cargo:rerun-if-changed={}", file.display());
+            if let Err(e) = read_annotations(file, &mut annotations) {
+                println!(
+                    "cargo:warning=Failed to read CLDR annotations from {}: {}",
+                    file.display(),
+                    e
+                );
+            }
+        }
+
+        writeln!(
+            out,
+            "const {}: &[(&str, Annotation)] = &[",
+            annotations_const(language)
+        )
+        .unwrap();
+        for (emoji, (tts, keywords)) in annotations.iter() {
+            writeln!(
+                out,
+                "    ({:?}, Annotation {{ tts: {:?}, keywords: &{:?} }}),",
+                emoji, tts, keywords
+            )
+            .unwrap();
+        }
+        out.push_str("];\n\n");
+    }
+
+    out.push_str("pub static LANGUAGES: &[(&str, &[(&str, Annotation)])] = &[\n");
+    for language in languages.iter() {
+        writeln!(
+            out,
+            "    ({:?}, {}),",
+            language,
+            annotations_const(language)
+        )
+        .unwrap();
+    }
+    out.push_str("];\n");
+
+    let out_file = Path::new(&env::var("OUT_DIR").unwrap()).join("annotations.rs");
+    fs::write(out_file, out).expect("Failed to write the annotations.");
+}
+
+/// Name of the generated constant holding the annotations of a language.
+fn annotations_const(language: &str) -> String {
+    format!("ANNOTATIONS_{}", language.to_uppercase().replace('-', "_"))
+}
+
+/// Reads a CLDR `annotations.json` (or `annotationsDerived`) file.
+fn read_annotations(file: &Path, annotations: &mut Annotations) -> Result<(), Box<dyn Error>> {
+    let json: serde_json::Value = serde_json::from_str(&fs::read_to_string(file)?)?;
+    let entries = json
+        .as_object()
+        .and_then(|root| root.values().next())
+        .and_then(|content| content["annotations"].as_object())
+        .ok_or("unexpected format")?;
+
+    for (emoji, annotation) in entries {
+        // "↑↑↑" means that the value is inherited from the parent locale.
+        let strings = |key: &str| -> Vec<String> {
+            annotation[key]
+                .as_array()
+                .map(|a| {
+                    a.iter()
+                        .filter_map(|s| s.as_str())
+                        .filter(|s| *s != "↑↑↑")
+                        .map(String::from)
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+        let emoji: String = emoji.chars().filter(|c| *c != '\u{fe0f}').collect();
+        let tts = strings("tts").into_iter().next().unwrap_or_default();
+        annotations.insert(emoji, (tts, strings("default")));
+    }
+    Ok(())
+}
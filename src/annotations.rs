@@ -0,0 +1,21 @@
+/// The CLDR annotation of an emoji.
+pub struct Annotation {
+    /// The short (text-to-speech) name of the emoji.
+    pub tts: &'static str,
+    /// Keywords describing the emoji, e.g. "face", "grin" for 😀.
+    pub keywords: &'static [&'static str],
+}
+
+// Generated by `build.rs`: the annotations of every bundled language, sorted by emoji.
+include!(concat!(env!("OUT_DIR"), "/annotations.rs"));
+
+/// Returns the annotation of the given emoji in the given language, if bundled.
+pub fn get(language: &str, code: &str) -> Option<&'static Annotation> {
+    let (_, annotations) = LANGUAGES.iter().find(|(l, _)| *l == language)?;
+    // CLDR does not use the variation selector.
+    let code: String = code.chars().filter(|c| *c != '\u{fe0f}').collect();
+    annotations
+        .binary_search_by(|(emoji, _)| (*emoji).cmp(code.as_str()))
+        .ok()
+        .map(|idx| &annotations[idx].1)
+}
@@ -0,0 +1,323 @@
+//! The command-line interface. Besides driving the picker, it gives the
+//! scripts access to the emoji database without opening the window.
+
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand};
+
+use crate::emoji::EmojiWrapper;
+use crate::emoji_model;
+use crate::history::EmojiHistory;
+use crate::ipc::Command;
+use crate::locale;
+use crate::settings::Settings;
+use crate::shortcut::OpenGroup;
+
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// Shows the picker if omitted.
+    #[command(subcommand)]
+    pub command: Option<CliCommand>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CliCommand {
+    /// Starts the picker in the background, without showing it.
+    Daemon,
+    /// Shows the picker, starting it if needed.
+    Show {
+        /// The group to open into.
+        #[arg(long)]
+        group: Option<OpenGroup>,
+        /// The text to filter the emojis with.
+        #[arg(long)]
+        query: Option<String>,
+        /// Prints the selected emoji instead of typing it, then exits.
+        /// The exit code is 1 if the picker is closed without selecting an emoji.
+        #[arg(long)]
+        print: bool,
+    },
+    /// Hides the picker.
+    Hide,
+    /// Shows the picker if it is hidden, hides it otherwise.
+    Toggle,
+    /// Exits the picker.
+    Quit,
+    /// Reloads the settings of the picker.
+    ReloadConfig,
+    /// Prints the emojis matching a query, the best first.
+    Search {
+        #[arg(required = true)]
+        query: Vec<String>,
+        /// The maximum number of emojis printed.
+        #[arg(short = 'n', long, default_value_t = 10)]
+        limit: usize,
+    },
+    /// Prints the name, code points, shortcodes and group of an emoji.
+    Info {
+        /// The emoji, or one of its shortcodes.
+        emoji: String,
+    },
+    /// Works with the settings file.
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommand {
+    /// Checks that the settings file is valid.
+    Check,
+}
+
+impl Default for CliCommand {
+    fn default() -> Self {
+        CliCommand::Show {
+            group: None,
+            query: None,
+            print: false,
+        }
+    }
+}
+
+/// What is left to do once the command-line is handled.
+pub enum Outcome {
+    /// The picker runs the command, or starts hidden without one.
+    Picker(Option<Command>),
+    /// A picker of its own shows up, and prints the selected emoji.
+    Print(Command),
+    /// The command did not need the picker.
+    Exit(ExitCode),
+}
+
+impl CliCommand {
+    /// Runs the commands working without the picker.
+    pub fn run(self) -> Outcome {
+        let command = match self {
+            CliCommand::Daemon => return Outcome::Picker(None),
+            CliCommand::Show {
+                group,
+                query,
+                print: true,
+            } => return Outcome::Print(Command::Show { group, query }),
+            CliCommand::Show { group, query, .. } => Command::Show { group, query },
+            CliCommand::Hide => Command::Hide,
+            CliCommand::Toggle => Command::Toggle,
+            CliCommand::Quit => Command::Quit,
+            CliCommand::ReloadConfig => Command::ReloadConfig,
+            CliCommand::Search { query, limit } => return Outcome::Exit(search(&query, limit)),
+            CliCommand::Info { emoji } => return Outcome::Exit(info(&emoji)),
+            CliCommand::Config {
+                command: ConfigCommand::Check,
+            } => return Outcome::Exit(check_config()),
+        };
+        Outcome::Picker(Some(command))
+    }
+}
+
+/// Parses the command-line.
+pub fn parse() -> Cli {
+    // The picker is not a console application on Windows: it must attach
+    // to the console it was run from to print anything.
+    #[cfg(target_os = "windows")]
+    unsafe {
+        use windows::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+        let _ = AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+    Cli::parse()
+}
+
+/// Sets the language of the settings, so that the names and keywords
+/// are the ones of the picker.
+fn set_language() {
+    let configured = Settings::load().ok().and_then(|settings| settings.locale);
+    locale::set_language(&locale::resolve(configured.as_deref()));
+}
+
+fn search(query: &[String], limit: usize) -> ExitCode {
+    set_language();
+    let results = emoji_model::search(&query.join(" "), &EmojiHistory::load());
+    if results.is_empty() {
+        return ExitCode::FAILURE;
+    }
+    for emoji in results.into_iter().take(limit) {
+        println!("{}\t{}", emoji.code(), emoji.localized_name());
+    }
+    ExitCode::SUCCESS
+}
+
+fn info(text: &str) -> ExitCode {
+    set_language();
+    let emoji = emojis::get(text)
+        .or_else(|| emojis::get_by_shortcode(text.trim_matches(':')))
+        .map(EmojiWrapper);
+    let Some(emoji) = emoji else {
+        eprintln!("Unknown emoji `{}`", text);
+        return ExitCode::FAILURE;
+    };
+
+    let code_points: Vec<String> = emoji
+        .code()
+        .chars()
+        .map(|c| format!("U+{:04X}", u32::from(c)))
+        .collect();
+    let shortcodes: Vec<String> = emoji.shortcodes().map(|s| format!(":{}:", s)).collect();
+    println!("Emoji:       {}", emoji.code());
+    println!("Name:        {}", emoji.localized_name());
+    println!("Code points: {}", code_points.join(" "));
+    println!("Shortcodes:  {}", shortcodes.join(" "));
+    println!("Group:       {}", emoji.group().group_name());
+    ExitCode::SUCCESS
+}
+
+fn check_config() -> ExitCode {
+    let Some(path) = Settings::path() else {
+        eprintln!("There is no config directory, the default settings are used.");
+        return ExitCode::SUCCESS;
+    };
+    if !path.exists() {
+        println!(
+            "{} does not exist, the default settings are used.",
+            path.display()
+        );
+        return ExitCode::SUCCESS;
+    }
+    match Settings::load() {
+        Ok(_) => {
+            println!("{} is valid.", path.display());
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("{}: {}", path.display(), e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Option<CliCommand> {
+        let args = std::iter::once("emoji-picker").chain(args.iter().copied());
+        Cli::try_parse_from(args).unwrap().command
+    }
+
+    fn outcome(args: &[&str]) -> Outcome {
+        parse(args).unwrap_or_default().run()
+    }
+
+    #[test]
+    fn declares_a_valid_interface() {
+        use clap::CommandFactory;
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn shows_the_picker_without_a_command() {
+        assert!(parse(&[]).is_none());
+        assert!(matches!(
+            outcome(&[]),
+            Outcome::Picker(Some(Command::Show {
+                group: None,
+                query: None
+            }))
+        ));
+    }
+
+    #[test]
+    fn sends_the_commands_to_the_picker() {
+        assert!(matches!(
+            outcome(&["hide"]),
+            Outcome::Picker(Some(Command::Hide))
+        ));
+        assert!(matches!(
+            outcome(&["toggle"]),
+            Outcome::Picker(Some(Command::Toggle))
+        ));
+        assert!(matches!(
+            outcome(&["quit"]),
+            Outcome::Picker(Some(Command::Quit))
+        ));
+        assert!(matches!(
+            outcome(&["reload-config"]),
+            Outcome::Picker(Some(Command::ReloadConfig))
+        ));
+    }
+
+    #[test]
+    fn starts_the_picker_hidden_as_a_daemon() {
+        assert!(matches!(outcome(&["daemon"]), Outcome::Picker(None)));
+    }
+
+    #[test]
+    fn shows_the_picker_into_a_group_and_filtered() {
+        let outcome = outcome(&["show", "--group", "flags", "--query", "red heart"]);
+        let Outcome::Picker(Some(Command::Show { group, query })) = outcome else {
+            panic!("the picker is not shown");
+        };
+        assert_eq!(group, Some(OpenGroup::Flags));
+        assert_eq!(query.as_deref(), Some("red heart"));
+    }
+
+    #[test]
+    fn prints_with_a_picker_of_its_own() {
+        let outcome = outcome(&["show", "--print", "--query", "cat"]);
+        let Outcome::Print(Command::Show { group, query }) = outcome else {
+            panic!("the picker does not print");
+        };
+        assert_eq!(group, None);
+        assert_eq!(query.as_deref(), Some("cat"));
+        assert!(matches!(
+            self::outcome(&["show", "--print"]),
+            Outcome::Print(Command::Show {
+                group: None,
+                query: None
+            })
+        ));
+    }
+
+    #[test]
+    fn rejects_an_unknown_group() {
+        let args = ["emoji-picker", "show", "--group", "nowhere"];
+        assert!(Cli::try_parse_from(args).is_err());
+    }
+
+    #[test]
+    fn searches_the_words_of_the_query() {
+        let Some(CliCommand::Search { query, limit }) = parse(&["search", "red", "heart"]) else {
+            panic!("not a search");
+        };
+        assert_eq!(query, ["red", "heart"]);
+        assert_eq!(limit, 10);
+        let Some(CliCommand::Search { limit, .. }) = parse(&["search", "-n", "3", "cat"]) else {
+            panic!("not a search");
+        };
+        assert_eq!(limit, 3);
+    }
+
+    #[test]
+    fn requires_a_query_to_search() {
+        assert!(Cli::try_parse_from(["emoji-picker", "search"]).is_err());
+        assert!(Cli::try_parse_from(["emoji-picker", "search", "-n", "3"]).is_err());
+    }
+
+    #[test]
+    fn reads_the_other_commands() {
+        assert!(matches!(
+            parse(&["info", ":thumbsup:"]),
+            Some(CliCommand::Info { emoji }) if emoji == ":thumbsup:"
+        ));
+        assert!(matches!(
+            parse(&["config", "check"]),
+            Some(CliCommand::Config {
+                command: ConfigCommand::Check
+            })
+        ));
+        assert!(Cli::try_parse_from(["emoji-picker", "info"]).is_err());
+        assert!(Cli::try_parse_from(["emoji-picker", "config"]).is_err());
+        assert!(Cli::try_parse_from(["emoji-picker", "open"]).is_err());
+    }
+}
@@ -0,0 +1,188 @@
+//! The clipboard of the desktop. The picker copies the emoji there when it
+//! cannot type it.
+//!
+//! On X11 and Wayland, the clipboard does not hold the data: the application
+//! owning it sends the data to the ones pasting. The copied text is served
+//! from a thread, until another application takes the clipboard.
+
+#[cfg(target_os = "linux")]
+mod wayland;
+#[cfg(windows)]
+mod win32;
+#[cfg(target_os = "linux")]
+mod x11;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ClipboardError {
+    #[cfg(windows)]
+    #[error("The clipboard is used by another application.")]
+    Busy,
+    #[cfg(target_os = "linux")]
+    #[error("Another application kept the ownership of the clipboard.")]
+    NotOwner,
+    #[cfg(target_os = "linux")]
+    #[error("Failed to connect to the display server. Reason: {0}")]
+    Connect(String),
+    #[cfg(target_os = "linux")]
+    #[error("The display server does not support the {0} protocol.")]
+    MissingProtocol(&'static str),
+    #[cfg(target_os = "linux")]
+    #[error("There is no display server to own the clipboard of.")]
+    NoDisplay,
+    #[cfg(target_os = "linux")]
+    #[error("The application owning the clipboard did not send its content in time.")]
+    Timeout,
+    #[cfg(target_os = "linux")]
+    #[error("Failed to receive the content of the clipboard. Reason: {0}")]
+    Io(#[from] std::io::Error),
+    #[cfg(not(any(windows, target_os = "linux")))]
+    #[error("The clipboard is not supported on this platform.")]
+    Unsupported,
+    #[cfg(windows)]
+    #[error("Failed to use the clipboard. Reason: {0}")]
+    Win32(#[from] windows::core::Error),
+    #[cfg(target_os = "linux")]
+    #[error("Failed to use the X11 clipboard. Reason: {0}")]
+    X11(#[from] x11rb::errors::ReplyOrIdError),
+    #[cfg(target_os = "linux")]
+    #[error("Failed to use the Wayland clipboard. Reason: {0}")]
+    Wayland(#[from] wayland_client::DispatchError),
+}
+
+/// The content of the clipboard, in every format it was available in.
+#[derive(Debug, Clone, Default)]
+pub struct Contents(Vec<(Format, Vec<u8>)>);
+
+/// A clipboard format.
+#[cfg(windows)]
+type Format = u32;
+/// A MIME type on Wayland, the name of a target atom on X11.
+#[cfg(not(windows))]
+type Format = String;
+
+pub trait Clipboard: Send + Sync {
+    /// Replaces the content of the clipboard with the text.
+    fn set_text(&self, text: &str) -> Result<(), ClipboardError>;
+    /// Returns the content of the clipboard, to restore it later.
+    fn save(&self) -> Result<Contents, ClipboardError>;
+    /// Replaces the content of the clipboard with a saved one.
+    fn restore(&self, contents: &Contents) -> Result<(), ClipboardError>;
+}
+
+/// The clipboard of the desktop the picker runs on.
+pub struct SystemClipboard;
+
+#[cfg(windows)]
+impl Clipboard for SystemClipboard {
+    fn set_text(&self, text: &str) -> Result<(), ClipboardError> {
+        win32::set_text(text)
+    }
+
+    fn save(&self) -> Result<Contents, ClipboardError> {
+        win32::save()
+    }
+
+    fn restore(&self, contents: &Contents) -> Result<(), ClipboardError> {
+        win32::restore(contents)
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Clipboard for SystemClipboard {
+    fn set_text(&self, text: &str) -> Result<(), ClipboardError> {
+        on_display(|| wayland::set_text(text), || x11::set_text(text))
+    }
+
+    fn save(&self) -> Result<Contents, ClipboardError> {
+        on_display(wayland::save, x11::save)
+    }
+
+    fn restore(&self, contents: &Contents) -> Result<(), ClipboardError> {
+        on_display(|| wayland::restore(contents), || x11::restore(contents))
+    }
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+impl Clipboard for SystemClipboard {
+    fn set_text(&self, _: &str) -> Result<(), ClipboardError> {
+        Err(ClipboardError::Unsupported)
+    }
+
+    fn save(&self) -> Result<Contents, ClipboardError> {
+        Err(ClipboardError::Unsupported)
+    }
+
+    fn restore(&self, _: &Contents) -> Result<(), ClipboardError> {
+        Err(ClipboardError::Unsupported)
+    }
+}
+
+/// Uses the Wayland clipboard, else the X11 one.
+/// GNOME does not have the data-control protocol, but XWayland shares
+/// the X11 clipboard with the Wayland applications.
+#[cfg(target_os = "linux")]
+fn on_display<T>(
+    wayland: impl FnOnce() -> Result<T, ClipboardError>,
+    x11: impl FnOnce() -> Result<T, ClipboardError>,
+) -> Result<T, ClipboardError> {
+    let has_wayland = std::env::var_os("WAYLAND_DISPLAY").is_some();
+    let has_x11 = std::env::var_os("DISPLAY").is_some();
+    on_displays(has_wayland, has_x11, wayland, x11)
+}
+
+#[cfg(target_os = "linux")]
+fn on_displays<T>(
+    has_wayland: bool,
+    has_x11: bool,
+    wayland: impl FnOnce() -> Result<T, ClipboardError>,
+    x11: impl FnOnce() -> Result<T, ClipboardError>,
+) -> Result<T, ClipboardError> {
+    if has_wayland {
+        match wayland() {
+            Err(ClipboardError::MissingProtocol(_)) if has_x11 => {}
+            result => return result,
+        }
+    }
+    if !has_x11 {
+        return Err(ClipboardError::NoDisplay);
+    }
+    x11()
+}
+
+#[cfg(test)]
+#[cfg(target_os = "linux")]
+mod tests {
+    use super::*;
+
+    fn missing() -> Result<&'static str, ClipboardError> {
+        Err(ClipboardError::MissingProtocol("data-control"))
+    }
+
+    #[test]
+    fn uses_the_wayland_clipboard_first() {
+        let result = on_displays(true, true, || Ok("wayland"), || Ok("x11"));
+        assert_eq!(result.unwrap(), "wayland");
+        let result = on_displays(false, true, || Ok("wayland"), || Ok("x11"));
+        assert_eq!(result.unwrap(), "x11");
+    }
+
+    #[test]
+    fn falls_back_to_xwayland_without_the_protocol() {
+        let result = on_displays(true, true, missing, || Ok("x11"));
+        assert_eq!(result.unwrap(), "x11");
+        let result = on_displays(true, false, missing, || Ok("x11"));
+        assert!(matches!(result, Err(ClipboardError::MissingProtocol(_))));
+    }
+
+    #[test]
+    fn keeps_the_other_wayland_errors() {
+        let result = on_displays(true, true, || Err(ClipboardError::Timeout), || Ok("x11"));
+        assert!(matches!(result, Err(ClipboardError::Timeout)));
+    }
+
+    #[test]
+    fn needs_a_display() {
+        let result = on_displays(false, false, || Ok("wayland"), || Ok("x11"));
+        assert!(matches!(result, Err(ClipboardError::NoDisplay)));
+    }
+}
@@ -0,0 +1,266 @@
+//! The Wayland clipboard, through the data-control protocol of wlroots (also
+//! implemented by KDE). The core wl_data_device cannot be used: it only sets
+//! the clipboard of a client having the keyboard focus, which the picker
+//! does not have when it copies.
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{Read, Write},
+    os::fd::AsFd,
+    sync::mpsc,
+    time::Duration,
+};
+
+use wayland_client::{
+    backend::ObjectId,
+    event_created_child,
+    globals::{registry_queue_init, GlobalListContents},
+    protocol::{wl_registry::WlRegistry, wl_seat::WlSeat},
+    Connection, Dispatch, EventQueue, Proxy, QueueHandle,
+};
+use wayland_protocols_wlr::data_control::v1::client::{
+    zwlr_data_control_device_v1::{self, ZwlrDataControlDeviceV1},
+    zwlr_data_control_manager_v1::ZwlrDataControlManagerV1,
+    zwlr_data_control_offer_v1::{self, ZwlrDataControlOfferV1},
+    zwlr_data_control_source_v1::{self, ZwlrDataControlSourceV1},
+};
+
+use super::{ClipboardError, Contents};
+
+/// The formats the text is offered in.
+const MIME_TYPES: [&str; 5] = [
+    "text/plain;charset=utf-8",
+    "text/plain",
+    "UTF8_STRING",
+    "STRING",
+    "TEXT",
+];
+
+/// How long the owner of the clipboard has to send a format.
+const RECEIVE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Sets the selection, and serves the text until another client sets it.
+pub fn set_text(text: &str) -> Result<(), ClipboardError> {
+    let served = MIME_TYPES
+        .iter()
+        .map(|mime_type| (mime_type.to_string(), text.as_bytes().to_vec()))
+        .collect();
+    Device::connect()?.serve(served)
+}
+
+/// Receives the selection in each of its formats.
+pub fn save() -> Result<Contents, ClipboardError> {
+    let mut device = Device::connect()?;
+    let Some(offer) = device.state.selection.take() else {
+        return Ok(Contents::default());
+    };
+    let mime_types = device.state.offers.remove(&offer.id()).unwrap_or_default();
+
+    let mut formats = Vec::new();
+    for mime_type in mime_types {
+        let (reader, writer) = rustix::pipe::pipe().map_err(std::io::Error::from)?;
+        offer.receive(mime_type.clone(), writer.as_fd());
+        drop(writer);
+        device.queue.roundtrip(&mut device.state)?;
+        if let Some(data) = read_in_time(File::from(reader)) {
+            formats.push((mime_type, data));
+        }
+    }
+    offer.destroy();
+    Ok(Contents(formats))
+}
+
+/// Sets the selection again, and serves the saved formats.
+/// The selection is cleared if nothing was saved.
+pub fn restore(contents: &Contents) -> Result<(), ClipboardError> {
+    let mut device = Device::connect()?;
+    if contents.0.is_empty() {
+        device.device.set_selection(None);
+        device.queue.roundtrip(&mut device.state)?;
+        return Ok(());
+    }
+    device.serve(contents.0.clone())
+}
+
+/// Reads the file until its end, unless the writer takes too long.
+fn read_in_time(mut file: File) -> Option<Vec<u8>> {
+    let (tx, rx) = mpsc::sync_channel(1);
+    std::thread::spawn(move || {
+        let mut data = Vec::new();
+        let _ = tx.send(file.read_to_end(&mut data).map(|_| data));
+    });
+    rx.recv_timeout(RECEIVE_TIMEOUT).ok()?.ok()
+}
+
+/// The data device of the first seat.
+struct Device {
+    queue: EventQueue<State>,
+    state: State,
+    manager: ZwlrDataControlManagerV1,
+    device: ZwlrDataControlDeviceV1,
+}
+
+impl Device {
+    /// Connects to the compositor. The current selection is known once connected.
+    fn connect() -> Result<Self, ClipboardError> {
+        let connection =
+            Connection::connect_to_env().map_err(|e| ClipboardError::Connect(e.to_string()))?;
+        let (globals, mut queue) = registry_queue_init::<State>(&connection)
+            .map_err(|e| ClipboardError::Connect(e.to_string()))?;
+        let handle = queue.handle();
+        let manager: ZwlrDataControlManagerV1 = globals
+            .bind(&handle, 1..=2, ())
+            .map_err(|_| ClipboardError::MissingProtocol("wlr-data-control"))?;
+        let seat: WlSeat = globals
+            .bind(&handle, 1..=1, ())
+            .map_err(|_| ClipboardError::MissingProtocol("wl_seat"))?;
+        let device = manager.get_data_device(&seat, &handle, ());
+
+        let mut state = State::default();
+        queue.roundtrip(&mut state)?;
+        Ok(Self {
+            queue,
+            state,
+            manager,
+            device,
+        })
+    }
+
+    /// Sets the selection, and serves the formats from a thread until
+    /// another client sets it.
+    fn serve(mut self, served: Vec<(String, Vec<u8>)>) -> Result<(), ClipboardError> {
+        let source = self.manager.create_data_source(&self.queue.handle(), ());
+        for (mime_type, _) in &served {
+            source.offer(mime_type.clone());
+        }
+        self.device.set_selection(Some(&source));
+        self.state.served = served;
+        self.queue.roundtrip(&mut self.state)?;
+        if self.state.cancelled {
+            return Err(ClipboardError::NotOwner);
+        }
+
+        std::thread::spawn(move || {
+            while !self.state.cancelled {
+                if let Err(e) = self.queue.blocking_dispatch(&mut self.state) {
+                    eprintln!("Failed to serve the Wayland clipboard. Reason: {}", e);
+                    break;
+                }
+            }
+            source.destroy();
+            self.device.destroy();
+        });
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct State {
+    /// The formats being served, with their data.
+    served: Vec<(String, Vec<u8>)>,
+    /// Whether another client set the selection.
+    cancelled: bool,
+    /// The formats of the offers of the other clients.
+    offers: HashMap<ObjectId, Vec<String>>,
+    /// The offer of the current selection, if it is not the picker's.
+    selection: Option<ZwlrDataControlOfferV1>,
+}
+
+impl State {
+    fn forget(&mut self, offer: ZwlrDataControlOfferV1) {
+        self.offers.remove(&offer.id());
+        offer.destroy();
+    }
+}
+
+impl Dispatch<ZwlrDataControlSourceV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        _: &ZwlrDataControlSourceV1,
+        event: zwlr_data_control_source_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            zwlr_data_control_source_v1::Event::Send { mime_type, fd } => {
+                let data = state.served.iter().find(|(served, _)| *served == mime_type);
+                if let Some((_, data)) = data {
+                    if let Err(e) = File::from(fd).write_all(data) {
+                        eprintln!("Failed to paste the Wayland clipboard. Reason: {}", e);
+                    }
+                }
+            }
+            zwlr_data_control_source_v1::Event::Cancelled => state.cancelled = true,
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<ZwlrDataControlDeviceV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        _: &ZwlrDataControlDeviceV1,
+        event: zwlr_data_control_device_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            zwlr_data_control_device_v1::Event::DataOffer { id } => {
+                state.offers.insert(id.id(), Vec::new());
+            }
+            zwlr_data_control_device_v1::Event::Selection { id } => {
+                if let Some(previous) = std::mem::replace(&mut state.selection, id) {
+                    state.forget(previous);
+                }
+            }
+            zwlr_data_control_device_v1::Event::PrimarySelection { id: Some(offer) } => {
+                state.forget(offer);
+            }
+            _ => {}
+        }
+    }
+
+    event_created_child!(State, ZwlrDataControlDeviceV1, [
+        zwlr_data_control_device_v1::EVT_DATA_OFFER_OPCODE => (ZwlrDataControlOfferV1, ()),
+    ]);
+}
+
+impl Dispatch<ZwlrDataControlOfferV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        offer: &ZwlrDataControlOfferV1,
+        event: zwlr_data_control_offer_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let zwlr_data_control_offer_v1::Event::Offer { mime_type } = event {
+            state.offers.entry(offer.id()).or_default().push(mime_type);
+        }
+    }
+}
+
+macro_rules! ignore_events {
+    ($($interface:ty, $user_data:ty;)*) => {$(
+        impl Dispatch<$interface, $user_data> for State {
+            fn event(
+                _: &mut Self,
+                _: &$interface,
+                _: <$interface as Proxy>::Event,
+                _: &$user_data,
+                _: &Connection,
+                _: &QueueHandle<Self>,
+            ) {
+            }
+        }
+    )*};
+}
+
+ignore_events! {
+    WlRegistry, GlobalListContents;
+    WlSeat, ();
+    ZwlrDataControlManagerV1, ();
+}
@@ -0,0 +1,171 @@
+use std::ops::RangeInclusive;
+
+use windows::Win32::{
+    Foundation::{GlobalFree, HANDLE, HGLOBAL, NO_ERROR},
+    System::{
+        DataExchange::{
+            CloseClipboard, EmptyClipboard, EnumClipboardFormats, GetClipboardData, OpenClipboard,
+            SetClipboardData,
+        },
+        Memory::{GlobalAlloc, GlobalLock, GlobalSize, GlobalUnlock, GMEM_MOVEABLE},
+        Ole::{
+            CF_BITMAP, CF_DSPBITMAP, CF_DSPENHMETAFILE, CF_DSPMETAFILEPICT, CF_ENHMETAFILE,
+            CF_GDIOBJFIRST, CF_GDIOBJLAST, CF_METAFILEPICT, CF_OWNERDISPLAY, CF_PALETTE,
+            CF_UNICODETEXT,
+        },
+    },
+};
+
+use super::{ClipboardError, Contents};
+
+/// The formats whose data is a handle to something else than memory.
+/// The bitmaps are also available as DIBs, from which Windows synthesizes them.
+const HANDLE_FORMATS: [u16; 8] = [
+    CF_BITMAP.0,
+    CF_METAFILEPICT.0,
+    CF_PALETTE.0,
+    CF_ENHMETAFILE.0,
+    CF_OWNERDISPLAY.0,
+    CF_DSPBITMAP.0,
+    CF_DSPMETAFILEPICT.0,
+    CF_DSPENHMETAFILE.0,
+];
+const GDI_OBJECT_FORMATS: RangeInclusive<u16> = CF_GDIOBJFIRST.0..=CF_GDIOBJLAST.0;
+
+pub fn set_text(text: &str) -> Result<(), ClipboardError> {
+    let utf16: Vec<u16> = text.encode_utf16().chain(std::iter::once(0)).collect();
+    let _opened = OpenedClipboard::open()?;
+    unsafe { EmptyClipboard() }?;
+    set(CF_UNICODETEXT.0.into(), &utf16)
+}
+
+/// Copies the data of every format stored in memory. The other ones
+/// (bitmaps, metafiles…) are handles, which are not saved.
+pub fn save() -> Result<Contents, ClipboardError> {
+    let _opened = OpenedClipboard::open()?;
+    let mut formats = Vec::new();
+    let mut format = unsafe { EnumClipboardFormats(0) };
+    while format != 0 {
+        if is_in_memory(format) {
+            if let Some(data) = unsafe { get(format) } {
+                formats.push((format, data));
+            }
+        }
+        format = unsafe { EnumClipboardFormats(format) };
+    }
+    Ok(Contents(formats))
+}
+
+/// Whether the data of the format is memory, which can be copied.
+fn is_in_memory(format: u32) -> bool {
+    u16::try_from(format).map_or(true, |format| {
+        !HANDLE_FORMATS.contains(&format) && !GDI_OBJECT_FORMATS.contains(&format)
+    })
+}
+
+pub fn restore(contents: &Contents) -> Result<(), ClipboardError> {
+    let _opened = OpenedClipboard::open()?;
+    unsafe { EmptyClipboard() }?;
+    for (format, data) in &contents.0 {
+        set(*format, data)?;
+    }
+    Ok(())
+}
+
+/// Returns a copy of the data of the format, if it is not empty.
+unsafe fn get(format: u32) -> Option<Vec<u8>> {
+    let memory = HGLOBAL(GetClipboardData(format).ok()?.0);
+    let size = GlobalSize(memory);
+    if size == 0 {
+        return None;
+    }
+    let addr = GlobalLock(memory) as *const u8;
+    if addr.is_null() {
+        return None;
+    }
+    let data = std::slice::from_raw_parts(addr, size).to_vec();
+    let _ = GlobalUnlock(memory);
+    Some(data)
+}
+
+/// Sets the data of a format. The clipboard must be open and emptied.
+fn set<T: Copy>(format: u32, data: &[T]) -> Result<(), ClipboardError> {
+    let memory = GlobalMemory::copy_of(data)?;
+    unsafe { SetClipboardData(format, memory.handle()) }?;
+    // The clipboard owns the memory now.
+    std::mem::forget(memory);
+    Ok(())
+}
+
+/// The clipboard, opened by this thread until dropped.
+struct OpenedClipboard;
+
+impl OpenedClipboard {
+    fn open() -> Result<Self, ClipboardError> {
+        unsafe { OpenClipboard(None) }.map_err(|_| ClipboardError::Busy)?;
+        Ok(Self)
+    }
+}
+
+impl Drop for OpenedClipboard {
+    fn drop(&mut self) {
+        let _ = unsafe { CloseClipboard() };
+    }
+}
+
+/// Movable global memory, the only one the clipboard accepts. Freed when dropped.
+struct GlobalMemory(HGLOBAL);
+
+impl GlobalMemory {
+    fn copy_of<T: Copy>(data: &[T]) -> Result<Self, ClipboardError> {
+        let memory = Self(unsafe { GlobalAlloc(GMEM_MOVEABLE, std::mem::size_of_val(data)) }?);
+        unsafe {
+            let addr = GlobalLock(memory.0) as *mut T;
+            if addr.is_null() {
+                return Err(windows::core::Error::from_win32().into());
+            }
+            std::ptr::copy_nonoverlapping(data.as_ptr(), addr, data.len());
+            // Unlocking the memory "fails" with NO_ERROR.
+            match GlobalUnlock(memory.0) {
+                Err(e) if e.code() != NO_ERROR.into() => return Err(e.into()),
+                _ => {}
+            }
+        }
+        Ok(memory)
+    }
+
+    fn handle(&self) -> HANDLE {
+        HANDLE(self.0 .0)
+    }
+}
+
+impl Drop for GlobalMemory {
+    fn drop(&mut self) {
+        let _ = unsafe { GlobalFree(self.0) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use windows::Win32::System::Ole::{CF_DIB, CF_HDROP, CF_TEXT};
+
+    use super::*;
+
+    #[test]
+    fn saves_the_formats_in_memory() {
+        for format in [CF_TEXT, CF_UNICODETEXT, CF_DIB, CF_HDROP] {
+            assert!(is_in_memory(format.0.into()), "{}", format.0);
+        }
+        // The registered formats, e.g. "HTML Format".
+        assert!(is_in_memory(0xc0a5));
+    }
+
+    #[test]
+    fn skips_the_handles_and_the_gdi_objects() {
+        for format in [CF_BITMAP, CF_METAFILEPICT, CF_PALETTE, CF_ENHMETAFILE] {
+            assert!(!is_in_memory(format.0.into()), "{}", format.0);
+        }
+        assert!(!is_in_memory(u32::from(CF_GDIOBJFIRST.0) + 1));
+        assert!(!is_in_memory(CF_GDIOBJLAST.0.into()));
+    }
+}
@@ -0,0 +1,383 @@
+use std::time::{Duration, Instant};
+
+use x11rb::{
+    connection::Connection,
+    errors::{ConnectionError, ReplyError, ReplyOrIdError},
+    protocol::{
+        xproto::{
+            Atom, AtomEnum, ConnectionExt as _, CreateWindowAux, EventMask, GetPropertyReply,
+            PropMode, SelectionNotifyEvent, SelectionRequestEvent, Window, WindowClass,
+            SELECTION_NOTIFY_EVENT,
+        },
+        Event,
+    },
+    rust_connection::RustConnection,
+    wrapper::ConnectionExt as _,
+    COPY_DEPTH_FROM_PARENT, CURRENT_TIME, NONE,
+};
+
+use super::{ClipboardError, Contents};
+
+/// How long the owner of the clipboard has to send a format.
+const CONVERT_TIMEOUT: Duration = Duration::from_millis(500);
+
+x11rb::atom_manager! {
+    Atoms: AtomsCookie {
+        CLIPBOARD,
+        TARGETS,
+        INCR,
+        UTF8_STRING,
+        TEXT,
+        TEXT_PLAIN_UTF8: b"text/plain;charset=utf-8",
+        TEXT_PLAIN: b"text/plain",
+        EMOJI_PICKER_SELECTION,
+    }
+}
+
+impl From<ConnectionError> for ClipboardError {
+    fn from(e: ConnectionError) -> Self {
+        ReplyOrIdError::from(e).into()
+    }
+}
+
+impl From<ReplyError> for ClipboardError {
+    fn from(e: ReplyError) -> Self {
+        ReplyOrIdError::from(e).into()
+    }
+}
+
+/// The targets describing the selection, rather than holding its content.
+const META_TARGETS: [&[u8]; 4] = [b"TARGETS", b"MULTIPLE", b"TIMESTAMP", b"SAVE_TARGETS"];
+
+/// Owns the CLIPBOARD selection, and serves the text until another
+/// application owns it.
+pub fn set_text(text: &str) -> Result<(), ClipboardError> {
+    set_text_on(None, text)
+}
+
+/// Owns the CLIPBOARD selection of the given X display, or of `$DISPLAY`.
+fn set_text_on(display: Option<&str>, text: &str) -> Result<(), ClipboardError> {
+    let owner = Owner::new(display)?;
+    let targets = owner
+        .text_targets()
+        .into_iter()
+        .map(|target| (target, text.as_bytes().to_vec()))
+        .collect();
+    owner.serve(targets)
+}
+
+/// Converts the selection to each of its targets. The targets stored in
+/// several pieces (INCR) are left out.
+pub fn save() -> Result<Contents, ClipboardError> {
+    save_on(None)
+}
+
+fn save_on(display: Option<&str>) -> Result<Contents, ClipboardError> {
+    let owner = Owner::new(display)?;
+    let clipboard = owner.atoms.CLIPBOARD;
+    let current = owner.connection.get_selection_owner(clipboard)?.reply()?;
+    if current.owner == NONE {
+        return Ok(Contents::default());
+    }
+
+    let Some(targets) = owner.convert(owner.atoms.TARGETS)? else {
+        return Ok(Contents::default());
+    };
+    let targets = targets.value32().into_iter().flatten();
+    let mut formats = Vec::new();
+    for target in targets {
+        let name = owner.connection.get_atom_name(target)?.reply()?.name;
+        if META_TARGETS.contains(&name.as_slice()) {
+            continue;
+        }
+        let Ok(Some(data)) = owner.convert(target) else {
+            continue;
+        };
+        if is_whole(&data, owner.atoms.INCR) {
+            formats.push((String::from_utf8_lossy(&name).into_owned(), data.value));
+        }
+    }
+    Ok(Contents(formats))
+}
+
+/// Whether the data of a target was sent at once, as bytes.
+fn is_whole(data: &GetPropertyReply, incr: Atom) -> bool {
+    data.format == 8 && data.type_ != incr
+}
+
+/// Owns the CLIPBOARD selection again, and serves the saved targets.
+/// The selection is cleared if nothing was saved.
+pub fn restore(contents: &Contents) -> Result<(), ClipboardError> {
+    restore_on(None, contents)
+}
+
+fn restore_on(display: Option<&str>, contents: &Contents) -> Result<(), ClipboardError> {
+    let owner = Owner::new(display)?;
+    if contents.0.is_empty() {
+        owner
+            .connection
+            .set_selection_owner(NONE, owner.atoms.CLIPBOARD, CURRENT_TIME)?;
+        owner.connection.flush()?;
+        return Ok(());
+    }
+    let targets = contents
+        .0
+        .iter()
+        .map(|(name, data)| {
+            let atom = owner.connection.intern_atom(false, name.as_bytes())?;
+            Ok((atom.reply()?.atom, data.clone()))
+        })
+        .collect::<Result<_, ClipboardError>>()?;
+    owner.serve(targets)
+}
+
+/// The targets served, with their data.
+type Targets = Vec<(Atom, Vec<u8>)>;
+
+/// A hidden window, owning the selection or receiving its content.
+struct Owner {
+    connection: RustConnection,
+    window: Window,
+    atoms: Atoms,
+}
+
+impl Owner {
+    fn new(display: Option<&str>) -> Result<Self, ClipboardError> {
+        let (connection, screen) =
+            x11rb::connect(display).map_err(|e| ClipboardError::Connect(e.to_string()))?;
+        let root = connection.setup().roots[screen].root;
+        let window = connection.generate_id()?;
+        connection.create_window(
+            COPY_DEPTH_FROM_PARENT,
+            window,
+            root,
+            0,
+            0,
+            1,
+            1,
+            0,
+            WindowClass::INPUT_ONLY,
+            x11rb::COPY_FROM_PARENT,
+            &CreateWindowAux::new(),
+        )?;
+        let atoms = Atoms::new(&connection)?.reply()?;
+        Ok(Self {
+            connection,
+            window,
+            atoms,
+        })
+    }
+
+    fn text_targets(&self) -> [Atom; 5] {
+        [
+            self.atoms.UTF8_STRING,
+            self.atoms.TEXT_PLAIN_UTF8,
+            self.atoms.TEXT_PLAIN,
+            self.atoms.TEXT,
+            AtomEnum::STRING.into(),
+        ]
+    }
+
+    /// Asks the owner of the selection to store a target in a property of the
+    /// window, then reads it. Returns `None` if the owner refused.
+    fn convert(&self, target: Atom) -> Result<Option<GetPropertyReply>, ClipboardError> {
+        let property = self.atoms.EMOJI_PICKER_SELECTION;
+        self.connection.convert_selection(
+            self.window,
+            self.atoms.CLIPBOARD,
+            target,
+            property,
+            CURRENT_TIME,
+        )?;
+        self.connection.flush()?;
+
+        let deadline = Instant::now() + CONVERT_TIMEOUT;
+        loop {
+            match self.connection.poll_for_event()? {
+                Some(Event::SelectionNotify(notify)) if notify.target == target => {
+                    if notify.property == NONE {
+                        return Ok(None);
+                    }
+                    break;
+                }
+                Some(_) => {}
+                None if Instant::now() > deadline => return Err(ClipboardError::Timeout),
+                None => std::thread::sleep(Duration::from_millis(5)),
+            }
+        }
+        let reply = self
+            .connection
+            .get_property(true, self.window, property, AtomEnum::ANY, 0, u32::MAX)?
+            .reply()?;
+        Ok(Some(reply))
+    }
+
+    /// Owns the selection, and serves the targets from a thread until
+    /// another application owns it.
+    fn serve(self, targets: Targets) -> Result<(), ClipboardError> {
+        let clipboard = self.atoms.CLIPBOARD;
+        self.connection
+            .set_selection_owner(self.window, clipboard, CURRENT_TIME)?;
+        let owner = self.connection.get_selection_owner(clipboard)?.reply()?;
+        if owner.owner != self.window {
+            return Err(ClipboardError::NotOwner);
+        }
+        std::thread::spawn(move || {
+            if let Err(e) = self.reply_until_cleared(&targets) {
+                eprintln!("Failed to serve the X11 clipboard. Reason: {}", e);
+            }
+        });
+        Ok(())
+    }
+
+    fn reply_until_cleared(&self, targets: &Targets) -> Result<(), ReplyOrIdError> {
+        loop {
+            match self.connection.wait_for_event()? {
+                Event::SelectionRequest(request) => self.reply(&request, targets)?,
+                Event::SelectionClear(_) => return Ok(()),
+                _ => {}
+            }
+        }
+    }
+
+    /// Stores the requested target, or the available ones, in the property
+    /// of the requestor, then notifies it.
+    fn reply(
+        &self,
+        request: &SelectionRequestEvent,
+        targets: &Targets,
+    ) -> Result<(), ReplyOrIdError> {
+        // Obsolete clients do not set the property.
+        let property = match request.property {
+            NONE => request.target,
+            property => property,
+        };
+
+        let data = targets.iter().find(|(target, _)| *target == request.target);
+        let stored = if request.target == self.atoms.TARGETS {
+            let mut available = vec![self.atoms.TARGETS];
+            available.extend(targets.iter().map(|(target, _)| *target));
+            self.connection.change_property32(
+                PropMode::REPLACE,
+                request.requestor,
+                property,
+                AtomEnum::ATOM,
+                &available,
+            )?;
+            true
+        } else if let Some((target, data)) = data {
+            self.connection.change_property8(
+                PropMode::REPLACE,
+                request.requestor,
+                property,
+                *target,
+                data,
+            )?;
+            true
+        } else {
+            false
+        };
+
+        let notify = SelectionNotifyEvent {
+            response_type: SELECTION_NOTIFY_EVENT,
+            sequence: 0,
+            time: request.time,
+            requestor: request.requestor,
+            selection: request.selection,
+            target: request.target,
+            property: if stored { property } else { NONE },
+        };
+        self.connection
+            .send_event(false, request.requestor, EventMask::NO_EVENT, notify)?;
+        self.connection.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::xvfb::Xvfb;
+
+    const INCR: Atom = 300;
+
+    fn reply(format: u8, type_: Atom) -> GetPropertyReply {
+        GetPropertyReply {
+            format,
+            type_,
+            value: b"data".to_vec(),
+            ..GetPropertyReply::default()
+        }
+    }
+
+    fn formats(contents: &Contents) -> Vec<(&str, &[u8])> {
+        contents
+            .0
+            .iter()
+            .map(|(name, data)| (name.as_str(), data.as_slice()))
+            .collect()
+    }
+
+    #[test]
+    fn keeps_the_data_sent_at_once_as_bytes() {
+        assert!(is_whole(&reply(8, AtomEnum::STRING.into()), INCR));
+        assert!(!is_whole(&reply(8, INCR), INCR));
+        assert!(!is_whole(&reply(32, AtomEnum::ATOM.into()), INCR));
+    }
+
+    #[test]
+    fn fails_to_connect_without_a_server() {
+        assert!(matches!(
+            save_on(Some(":999")),
+            Err(ClipboardError::Connect(_))
+        ));
+        assert!(matches!(
+            set_text_on(Some(":999"), "👍"),
+            Err(ClipboardError::Connect(_))
+        ));
+    }
+
+    #[test]
+    #[ignore = "needs Xvfb"]
+    fn copies_saves_and_restores_the_clipboard() {
+        let xvfb = Xvfb::start();
+        let display = Some(xvfb.display.as_str());
+        assert!(save_on(display).unwrap().0.is_empty());
+
+        set_text_on(display, "👍").unwrap();
+        let saved = save_on(display).unwrap();
+        assert_eq!(
+            formats(&saved),
+            [
+                ("UTF8_STRING", "👍".as_bytes()),
+                ("text/plain;charset=utf-8", "👍".as_bytes()),
+                ("text/plain", "👍".as_bytes()),
+                ("TEXT", "👍".as_bytes()),
+                ("STRING", "👍".as_bytes()),
+            ]
+        );
+
+        // The targets describing the selection are not saved.
+        let copied = Contents(vec![
+            ("text/html".to_owned(), b"<b>hi</b>".to_vec()),
+            ("TIMESTAMP".to_owned(), vec![0; 4]),
+            ("UTF8_STRING".to_owned(), b"hi".to_vec()),
+        ]);
+        restore_on(display, &copied).unwrap();
+        let saved = save_on(display).unwrap();
+        assert_eq!(
+            formats(&saved),
+            [
+                ("text/html", b"<b>hi</b>".as_slice()),
+                ("UTF8_STRING", b"hi".as_slice())
+            ]
+        );
+
+        set_text_on(display, "🎉").unwrap();
+        restore_on(display, &saved).unwrap();
+        assert_eq!(formats(&save_on(display).unwrap()), formats(&saved));
+
+        restore_on(display, &Contents::default()).unwrap();
+        assert!(save_on(display).unwrap().0.is_empty());
+    }
+}
@@ -1,4 +1,26 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use crate::annotations::{self, Annotation};
+use crate::locale::{self, Text};
+
+/// Directory of the Twemoji images, unless the settings say otherwise.
+pub const DEFAULT_IMAGE_DIR: &str = "./emojis/twemoji/assets/svg";
+
+/// Directory of the emoji images. `None` means the default one.
+static IMAGE_DIR: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+pub fn set_image_dir(dir: &Path) {
+    *IMAGE_DIR.write().unwrap() = Some(dir.to_owned());
+}
+
+fn image_dir() -> PathBuf {
+    IMAGE_DIR
+        .read()
+        .unwrap()
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_IMAGE_DIR))
+}
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct EmojiWrapper(pub &'static emojis::Emoji);
@@ -20,6 +42,48 @@ impl EmojiWrapper {
         self.0.shortcodes()
     }
 
+    pub fn group(&self) -> EmojiGroupWrapper {
+        EmojiGroupWrapper(self.0.group())
+    }
+
+    /// Returns the name of the emoji in the current language.
+    pub fn localized_name(&self) -> &str {
+        self.tts_name().unwrap_or(self.name())
+    }
+
+    /// Returns the CLDR annotations of the emoji, in the current language
+    /// then in English.
+    fn annotations(&self) -> impl Iterator<Item = &'static Annotation> {
+        let code = self.0.as_str();
+        [locale::language(), locale::FALLBACK_LANGUAGE]
+            .into_iter()
+            .filter_map(move |language| annotations::get(language, code))
+    }
+
+    /// Returns the CLDR text-to-speech name of the emoji, if bundled.
+    pub fn tts_name(&self) -> Option<&'static str> {
+        self.annotations()
+            .map(|a| a.tts)
+            .find(|tts| !tts.is_empty())
+    }
+
+    /// Returns the CLDR keywords of the emoji, if bundled.
+    pub fn keywords(&self) -> impl Iterator<Item = &'static str> {
+        self.annotations()
+            .map(|a| a.keywords)
+            .find(|keywords| !keywords.is_empty())
+            .unwrap_or_default()
+            .iter()
+            .copied()
+    }
+
+    /// Returns the emoji without any skin tone modifier.
+    pub fn base(&self) -> Self {
+        self.skin_tones()
+            .and_then(|mut skin_tones| skin_tones.next())
+            .unwrap_or(*self)
+    }
+
     pub fn skin_tone(&self) -> Option<u16> {
         self.0.skin_tone().and_then(EmojiWrapper::skin_tone_idx)
     }
@@ -61,24 +125,9 @@ impl EmojiWrapper {
 pub struct EmojiGroupWrapper(pub emojis::Group);
 
 impl EmojiGroupWrapper {
+    /// Returns the title of the group in the current language.
     pub fn group_name(&self) -> &'static str {
-        (*self).into()
-    }
-}
-
-impl From<EmojiGroupWrapper> for &'static str {
-    fn from(group: EmojiGroupWrapper) -> &'static str {
-        match group.0 {
-            emojis::Group::Activities => "Activities",
-            emojis::Group::AnimalsAndNature => "Animals & Nature",
-            emojis::Group::Flags => "Flags",
-            emojis::Group::FoodAndDrink => "Food & Drink",
-            emojis::Group::Objects => "Objects",
-            emojis::Group::PeopleAndBody => "People & Body",
-            emojis::Group::SmileysAndEmotion => "Smileys & Emotion",
-            emojis::Group::Symbols => "Symbols",
-            emojis::Group::TravelAndPlaces => "Travel & Places",
-        }
+        locale::tr(Text::Group(self.0))
     }
 }
 
@@ -104,8 +153,16 @@ impl TwemojiFilename for EmojiWrapper {
         codes.join("-")
     }
 
+    /// Returns the SVG image of the emoji in the image directory, or the PNG one
+    /// if there is no SVG (e.g. with the `72x72` directory of Twemoji.)
     fn get_filename_path(&self) -> PathBuf {
         let filename = self.get_filename();
-        PathBuf::from(format!("./emojis/twemoji/assets/svg/{filename}.svg"))
+        let dir = image_dir();
+        let svg = dir.join(format!("{filename}.svg"));
+        if svg.exists() {
+            svg
+        } else {
+            dir.join(format!("{filename}.png"))
+        }
     }
 }
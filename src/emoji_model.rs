@@ -1,7 +1,12 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 
 use crate::{
     emoji::{EmojiGroupWrapper, EmojiWrapper},
+    favorites::Favorites,
+    history::EmojiHistory,
+    locale::{tr, Text},
+    search::{self, Query},
+    shortcut::OpenGroup,
     EmojiGroupModel, EmojiModel, EmojiSkinToneModel,
 };
 use slint::{Model, ModelNotify, ModelRc, SharedString, VecModel};
@@ -14,7 +19,7 @@ impl From<EmojiWrapper> for EmojiModel {
         let image = slint::Image::load_from_path(&filename);
 
         EmojiModel {
-            name: e.name().into(),
+            name: e.localized_name().into(),
             code: e.code().into(),
             image: image.unwrap_or_default(),
             shortcodes: ModelRc::new(VecModel::from(e.shortcodes().map(|x| SharedString::from(x)).collect::<Vec<_>>())),
@@ -48,48 +53,65 @@ impl TryFrom<EmojiWrapper> for EmojiSkinToneModel {
     }
 }
 
+/// Converts the emoji without its skin tones, so that the exact
+/// variant is displayed whatever the selected skin tone is.
+fn exact_emoji_model(e: EmojiWrapper) -> EmojiModel {
+    EmojiModel {
+        skin_tones: ModelRc::default(),
+        ..e.into()
+    }
+}
+
 // Model of a list of emojis.
 
 /// A model that contains a list of emojis.
+/// A hidden list has no rows, so its group is not displayed.
 struct VecEmojiListModel {
     initial: RefCell<Vec<EmojiModel>>,
     vec: RefCell<Vec<EmojiModel>>,
-    filter: RefCell<String>,
+    visible: Cell<bool>,
     notify: ModelNotify,
 }
 
 impl VecEmojiListModel {
-    pub fn new(emojis: Vec<EmojiWrapper>) -> Self {
-        let emojis: Vec<EmojiModel> = emojis.into_iter().map(|x| x.into()).collect();
+    pub fn new(emojis: Vec<EmojiModel>) -> Self {
         Self {
             initial: RefCell::new(emojis.clone()),
             vec: RefCell::new(emojis),
+            visible: Cell::new(true),
             notify: ModelNotify::default(),
-            filter: RefCell::new(String::new()),
         }
     }
 
-    fn filter_down(&self, filter: String) {
-        let filter = filter.to_lowercase();
-        let mut emojis = self.vec.borrow_mut();
-        VecEmojiListModel::retain_search(&mut emojis, &filter);
-        self.filter.replace(filter);
-        self.notify.reset()
+    /// Replaces the emojis of the list.
+    fn set_emojis(&self, emojis: Vec<EmojiModel>) {
+        self.initial.replace(emojis);
+        self.refresh();
     }
 
-    pub fn filter_up(&self, filter: String) {
-        let filter = filter.to_lowercase();
-        let mut emojis = self.initial.borrow().clone();
-        VecEmojiListModel::retain_search(&mut emojis, &filter);
-        self.filter.replace(filter);
+    /// Shows or hides the emojis of the list.
+    fn set_visible(&self, visible: bool) {
+        if self.visible.replace(visible) != visible {
+            self.refresh();
+        }
+    }
+
+    fn refresh(&self) {
+        let emojis = if self.visible.get() {
+            self.initial.borrow().clone()
+        } else {
+            vec![]
+        };
         self.vec.replace(emojis);
         self.notify.reset()
     }
 
-    fn retain_search(v: &mut Vec<EmojiModel>, filter: &str) {
-        v.retain(|x| {
-            x.name.contains(&filter) || x.shortcodes.iter().any(|s| s.contains(&filter))
-        })
+    fn first_image(&self) -> slint::Image {
+        self.initial
+            .borrow()
+            .first()
+            .map(|e| e.image.clone())
+            .unwrap_or_default()
     }
 }
 
@@ -119,65 +141,150 @@ impl Model for VecEmojiListModel {
 
 // Model of emoji groups
 
+/// Index of the "Favorites" group.
+const FAVORITES_GROUP_IDX: usize = 0;
+/// Index of the "Best matches" group, only filled while filtering.
+const BEST_MATCHES_GROUP_IDX: usize = 1;
+/// Index of the "Recently used" group.
+const RECENT_GROUP_IDX: usize = 2;
+
+/// Returns the index of the group a shortcut opens into.
+pub fn group_index(group: OpenGroup) -> usize {
+    use emojis::Group;
+    let emoji_group = match group {
+        OpenGroup::Favorites => return FAVORITES_GROUP_IDX,
+        OpenGroup::RecentlyUsed => return RECENT_GROUP_IDX,
+        OpenGroup::SmileysAndEmotion => Group::SmileysAndEmotion,
+        OpenGroup::PeopleAndBody => Group::PeopleAndBody,
+        OpenGroup::AnimalsAndNature => Group::AnimalsAndNature,
+        OpenGroup::FoodAndDrink => Group::FoodAndDrink,
+        OpenGroup::TravelAndPlaces => Group::TravelAndPlaces,
+        OpenGroup::Activities => Group::Activities,
+        OpenGroup::Objects => Group::Objects,
+        OpenGroup::Symbols => Group::Symbols,
+        OpenGroup::Flags => Group::Flags,
+    };
+    // The emoji groups follow the special ones, in Unicode order.
+    let position = Group::iter().position(|g| g == emoji_group).unwrap();
+    RECENT_GROUP_IDX + 1 + position
+}
+
+/// Returns every emoji matching the filter, the best first,
+/// as in the "Best matches" group.
+pub fn search(filter: &str, history: &EmojiHistory) -> Vec<EmojiWrapper> {
+    let query = Query::new(filter);
+    let candidates = emojis::iter().map(|e| (EmojiWrapper(e), EmojiWrapper(e)));
+    search::rank(&query, candidates, &history.frecencies())
+}
+
+/// Creates a group with the given title, its image being the first emoji of the list.
+fn group_model(title: &str, model: VecEmojiListModel) -> EmojiGroupModel {
+    EmojiGroupModel {
+        title: title.into(),
+        image: model.first_image(),
+        emojis: ModelRc::new(model),
+        pinned: false,
+    }
+}
+
 /// A model that contains a list of groups of emojis.
+/// The first groups contain the favorites, the ranked results of the filter
+/// and the recently used emojis. While a filter is active, only the ranked
+/// results are shown.
 pub struct VecEmojiGroupModel {
     vec: RefCell<Vec<EmojiGroupModel>>,
-    filter: RefCell<String>,
+    /// Every emoji, in Unicode order, that can be found by filtering.
+    catalog: Vec<(EmojiWrapper, EmojiModel)>,
     notify: ModelNotify,
 }
 
 impl VecEmojiGroupModel {
-    pub fn new() -> Self {
-        let mut vec = Vec::new();
+    pub fn new(favorites: &Favorites, history: &EmojiHistory) -> Self {
+        let pinned = favorites.emojis().map(exact_emoji_model).collect();
+        let recent = history.emojis().map(exact_emoji_model).collect();
+        let mut vec = vec![
+            EmojiGroupModel {
+                pinned: true,
+                ..group_model(tr(Text::Favorites), VecEmojiListModel::new(pinned))
+            },
+            group_model(tr(Text::BestMatches), VecEmojiListModel::new(vec![])),
+            group_model(tr(Text::RecentlyUsed), VecEmojiListModel::new(recent)),
+        ];
+        let mut catalog = Vec::new();
         for group in emojis::Group::iter() {
-            let emojis = group.emojis().map(EmojiWrapper).collect();
+            let emojis: Vec<EmojiModel> = group.emojis().map(|e| EmojiWrapper(e).into()).collect();
+            catalog.extend(group.emojis().map(EmojiWrapper).zip(emojis.iter().cloned()));
             let model = VecEmojiListModel::new(emojis);
-            let model = ModelRc::new(model);
-            let group_model = EmojiGroupModel {
-                title: EmojiGroupWrapper(group).group_name().into(),
-                image: model
-                    .iter()
-                    .next()
-                    .map(|e| e.image.clone())
-                    .unwrap_or_default(),
-                emojis: model,
-            };
-            vec.push(group_model);
+            vec.push(group_model(EmojiGroupWrapper(group).group_name(), model));
         }
 
         Self {
             vec: RefCell::new(vec),
-            filter: RefCell::new(String::new()),
+            catalog,
             notify: ModelNotify::default(),
         }
     }
 
-    pub fn filter(&self, filter: String) {
-        let is_filter_down = {
-            let old_filter = self.filter.borrow();
-            let filter = filter.to_lowercase();
-            filter.len() > old_filter.len()
-        };
-        if is_filter_down {
-            for group in self.vec.borrow_mut().iter_mut() {
-                group
-                    .emojis
-                    .as_any()
-                    .downcast_ref::<VecEmojiListModel>()
-                    .unwrap()
-                    .filter_down(filter.clone());
-            }
+    fn list(&self, idx: usize) -> ModelRc<EmojiModel> {
+        self.vec.borrow()[idx].emojis.clone()
+    }
+
+    /// Filters the emojis. While the filter is not empty, the matches are shown
+    /// as a single ranked list instead of the groups.
+    pub fn filter(&self, filter: String, history: &EmojiHistory) {
+        let query = Query::new(&filter);
+        let matches = if query.is_empty() {
+            vec![]
         } else {
-            for group in self.vec.borrow_mut().iter_mut() {
-                group
-                    .emojis
-                    .as_any()
-                    .downcast_ref::<VecEmojiListModel>()
-                    .unwrap()
-                    .filter_up(filter.clone());
-            }
+            let candidates = self.catalog.iter().map(|(emoji, model)| (*emoji, model));
+            search::rank(&query, candidates, &history.frecencies())
+                .into_iter()
+                .cloned()
+                .collect()
         };
-        self.filter.replace(filter);
+        self.set_group_emojis(BEST_MATCHES_GROUP_IDX, matches);
+
+        for idx in (0..self.row_count()).filter(|idx| *idx != BEST_MATCHES_GROUP_IDX) {
+            self.list(idx)
+                .as_any()
+                .downcast_ref::<VecEmojiListModel>()
+                .unwrap()
+                .set_visible(query.is_empty());
+        }
+    }
+
+    /// Returns the best match of the filter, the first emoji shown while filtering.
+    /// Returns None if there is no filter or nothing matches it.
+    pub fn first_match(&self) -> Option<EmojiModel> {
+        self.list(BEST_MATCHES_GROUP_IDX).row_data(0)
+    }
+
+    /// Replaces the emojis of a group, and updates its image accordingly.
+    fn set_group_emojis(&self, idx: usize, emojis: Vec<EmojiModel>) {
+        {
+            let mut vec = self.vec.borrow_mut();
+            let group = &mut vec[idx];
+            let model = group
+                .emojis
+                .as_any()
+                .downcast_ref::<VecEmojiListModel>()
+                .unwrap();
+            model.set_emojis(emojis);
+            group.image = model.first_image();
+        }
+        self.notify.row_changed(idx);
+    }
+
+    /// Replaces the emojis of the "Favorites" group.
+    pub fn set_favorites(&self, favorites: Vec<EmojiWrapper>) {
+        let favorites = favorites.into_iter().map(exact_emoji_model).collect();
+        self.set_group_emojis(FAVORITES_GROUP_IDX, favorites);
+    }
+
+    /// Replaces the emojis of the "Recently used" group.
+    pub fn set_recent(&self, recent: Vec<EmojiWrapper>) {
+        let recent = recent.into_iter().map(exact_emoji_model).collect();
+        self.set_group_emojis(RECENT_GROUP_IDX, recent);
     }
 }
 
@@ -0,0 +1,188 @@
+use std::{fs, io, path::PathBuf};
+
+use crate::emoji::EmojiWrapper;
+
+/// Name of the file, in the user's config directory, where the favorites are stored.
+const FAVORITES_FILE: &str = "favorites.txt";
+
+const FAVORITES_HEADER: &str = "\
+# Favorite emojis, one per line, in the order they are displayed.
+# Lines starting with # are ignored.
+";
+
+/// The emojis pinned by the user, in the order they are displayed.
+/// As for the history, the exact skin tone variant is stored.
+///
+/// The file can be edited by hand: it is read again every time the picker opens.
+pub struct Favorites {
+    codes: Vec<String>,
+    path: Option<PathBuf>,
+}
+
+impl Favorites {
+    /// Loads the favorites from the user's config directory.
+    /// A missing or unreadable file results in no favorites.
+    pub fn load() -> Self {
+        let path = crate::paths::config_file(FAVORITES_FILE);
+        let mut favorites = Self {
+            codes: vec![],
+            path,
+        };
+        favorites.reload();
+        favorites
+    }
+
+    /// Reads the favorites file again, e.g. after the user edited it.
+    /// If it was removed or cannot be read anymore, there are no favorites.
+    pub fn reload(&mut self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        self.codes.clear();
+        let Ok(content) = fs::read_to_string(path) else {
+            return;
+        };
+        for code in content.lines().map(str::trim) {
+            if !code.starts_with('#') && emojis::get(code).is_some() && !self.contains(code) {
+                self.codes.push(code.to_owned());
+            }
+        }
+    }
+
+    /// Writes the favorites to the user's config directory.
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let codes: String = self.codes.iter().map(|code| format!("{code}\n")).collect();
+        fs::write(path, format!("{FAVORITES_HEADER}{codes}"))
+    }
+
+    pub fn contains(&self, code: &str) -> bool {
+        self.codes.iter().any(|c| c == code)
+    }
+
+    /// Adds the code at the end of the favorites.
+    pub fn pin(&mut self, code: &str) {
+        if emojis::get(code).is_some() && !self.contains(code) {
+            self.codes.push(code.to_owned());
+        }
+    }
+
+    pub fn unpin(&mut self, code: &str) {
+        self.codes.retain(|c| c != code);
+    }
+
+    /// Moves the code by `offset` positions (negative to move it earlier.)
+    pub fn move_by(&mut self, code: &str, offset: isize) {
+        let Some(idx) = self.codes.iter().position(|c| c == code) else {
+            return;
+        };
+        let new_idx = idx.saturating_add_signed(offset).min(self.codes.len() - 1);
+        let code = self.codes.remove(idx);
+        self.codes.insert(new_idx, code);
+    }
+
+    /// Returns the favorite emojis, in order.
+    pub fn emojis(&self) -> impl Iterator<Item = EmojiWrapper> + '_ {
+        self.codes
+            .iter()
+            .filter_map(|code| emojis::get(code))
+            .map(EmojiWrapper)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pinned(codes: &[&str]) -> Favorites {
+        Favorites {
+            codes: codes.iter().map(|code| code.to_string()).collect(),
+            path: None,
+        }
+    }
+
+    fn temp_file(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("emoji-picker-{name}-{}", std::process::id()))
+    }
+
+    #[test]
+    fn moves_a_favorite_by_the_offset() {
+        let mut favorites = pinned(&["👍", "🎉", "❤️", "😀"]);
+        favorites.move_by("🎉", 2);
+        assert_eq!(favorites.codes, ["👍", "❤️", "😀", "🎉"]);
+        favorites.move_by("😀", -1);
+        assert_eq!(favorites.codes, ["👍", "😀", "❤️", "🎉"]);
+    }
+
+    #[test]
+    fn stops_a_favorite_at_the_ends() {
+        let mut favorites = pinned(&["👍", "🎉", "❤️"]);
+        favorites.move_by("👍", -1);
+        assert_eq!(favorites.codes, ["👍", "🎉", "❤️"]);
+        favorites.move_by("❤️", 1);
+        assert_eq!(favorites.codes, ["👍", "🎉", "❤️"]);
+        favorites.move_by("🎉", isize::MIN);
+        assert_eq!(favorites.codes, ["🎉", "👍", "❤️"]);
+        favorites.move_by("🎉", isize::MAX);
+        assert_eq!(favorites.codes, ["👍", "❤️", "🎉"]);
+    }
+
+    #[test]
+    fn ignores_the_move_of_an_unknown_code() {
+        let mut favorites = pinned(&["👍", "🎉"]);
+        favorites.move_by("❤️", 1);
+        assert_eq!(favorites.codes, ["👍", "🎉"]);
+        let mut favorites = pinned(&[]);
+        favorites.move_by("❤️", -1);
+        assert!(favorites.codes.is_empty());
+    }
+
+    #[test]
+    fn pins_an_emoji_once() {
+        let mut favorites = pinned(&["👍"]);
+        favorites.pin("🎉");
+        favorites.pin("👍");
+        favorites.pin("thumbsup");
+        assert_eq!(favorites.codes, ["👍", "🎉"]);
+    }
+
+    #[test]
+    fn reloads_the_file_edited_by_the_user() {
+        let path = temp_file("favorites-edited");
+        let mut favorites = pinned(&["👍", "🎉"]);
+        favorites.path = Some(path.clone());
+        favorites.save().unwrap();
+
+        fs::write(&path, "# Mine\n❤️\n  👍  \nnot an emoji\n❤️\n").unwrap();
+        favorites.reload();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(favorites.codes, ["❤️", "👍"]);
+    }
+
+    #[test]
+    fn reads_the_file_it_saves() {
+        let path = temp_file("favorites-saved");
+        let mut saved = pinned(&["👍🏽", "🎉"]);
+        saved.path = Some(path.clone());
+        saved.save().unwrap();
+
+        let mut read = pinned(&[]);
+        read.path = Some(path.clone());
+        read.reload();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(read.codes, ["👍🏽", "🎉"]);
+    }
+
+    #[test]
+    fn clears_the_favorites_when_the_file_is_removed() {
+        let mut favorites = pinned(&["👍"]);
+        favorites.path = Some(temp_file("favorites-removed"));
+        favorites.reload();
+        assert!(favorites.codes.is_empty());
+    }
+}
@@ -1,5 +1,6 @@
 use crate::poller::Poller;
 use std::sync::{mpsc, Mutex};
+use std::time::Duration;
 
 /// Represents a handler function.
 type HandlerFn<'a, Args> = dyn Fn(&Args) + Send + 'a;
@@ -105,9 +106,9 @@ impl<Args> NotifierPoller<Args>
 where
     Args: Send + 'static,
 {
-    pub fn new(mut f: impl FnMut() -> Option<Args> + Send + 'static) -> Self {
+    pub fn new(interval: Duration, mut f: impl FnMut() -> Option<Args> + Send + 'static) -> Self {
         let (tx, rx) = mpsc::sync_channel::<Args>(10);
-        let poller = Poller::new(move || {
+        let poller = Poller::new(interval, move || {
             if let Some(t) = f() {
                 tx.send(t).unwrap();
             }
@@ -180,7 +180,7 @@ pub fn generate_handlers<'a>(ui: &EmojiPickerWindow) -> Option<OutsideClickHandl
     let on_open_handler = Handler::new(move |args: &(SharedApp, NotifiersArgs)| {
         let (app, reason) = args;
 
-        if *reason != NotifierReason::Shortcut {
+        if !reason.is_shortcut() {
             return;
         }
 
@@ -0,0 +1,153 @@
+//! The caret of the focused application, through the AT-SPI2 accessibility bus.
+//! AT-SPI cannot be asked which object has the focus: it is the last one
+//! having told that it got the focus, or that its caret moved.
+
+use std::sync::{Arc, Mutex};
+
+use zbus::{
+    blocking::{Connection, ConnectionBuilder, MessageIterator, Proxy},
+    zvariant::{OwnedObjectPath, OwnedValue},
+    MatchRule, MessageType,
+};
+
+use crate::placement::Rect;
+
+const A11Y_BUS_DESTINATION: &str = "org.a11y.Bus";
+const A11Y_BUS_PATH: &str = "/org/a11y/bus";
+const A11Y_BUS_INTERFACE: &str = "org.a11y.Bus";
+const REGISTRY_DESTINATION: &str = "org.a11y.atspi.Registry";
+const REGISTRY_PATH: &str = "/org/a11y/atspi/registry";
+const REGISTRY_INTERFACE: &str = "org.a11y.atspi.Registry";
+const OBJECT_EVENT_INTERFACE: &str = "org.a11y.atspi.Event.Object";
+const TEXT_INTERFACE: &str = "org.a11y.atspi.Text";
+const COMPONENT_INTERFACE: &str = "org.a11y.atspi.Component";
+
+/// The events telling which object has the focus. The applications only
+/// send the events someone registered for.
+const FOCUS_EVENTS: [&str; 2] = ["object:state-changed:focused", "object:text-caret-moved"];
+
+/// The coordinates of the extents are relative to the screen.
+const COORD_TYPE_SCREEN: u32 = 0;
+
+#[derive(Debug, thiserror::Error)]
+pub enum AtspiError {
+    #[error("Failed to talk to the accessibility bus. Reason: {0}")]
+    Dbus(#[from] zbus::Error),
+}
+
+/// An accessible object, by the bus name of its application and its path.
+#[derive(Debug, Clone)]
+struct Accessible {
+    destination: String,
+    path: OwnedObjectPath,
+}
+
+/// Keeps track of the focused object, from a thread listening to the events.
+pub struct FocusTracker {
+    connection: Connection,
+    focused: Arc<Mutex<Option<Accessible>>>,
+}
+
+impl FocusTracker {
+    pub fn start() -> Result<Self, AtspiError> {
+        let address: String = Proxy::new(
+            &Connection::session()?,
+            A11Y_BUS_DESTINATION,
+            A11Y_BUS_PATH,
+            A11Y_BUS_INTERFACE,
+        )?
+        .call("GetAddress", &())?;
+        let connection = ConnectionBuilder::address(address.as_str())?.build()?;
+
+        let rule = MatchRule::builder()
+            .msg_type(MessageType::Signal)
+            .interface(OBJECT_EVENT_INTERFACE)?
+            .build();
+        let events = MessageIterator::for_match_rule(rule, &connection, None)?;
+        let registry = Proxy::new(
+            &connection,
+            REGISTRY_DESTINATION,
+            REGISTRY_PATH,
+            REGISTRY_INTERFACE,
+        )?;
+        for event in FOCUS_EVENTS {
+            registry.call_method("RegisterEvent", &(event,))?;
+        }
+
+        let focused = Arc::new(Mutex::new(None));
+        std::thread::spawn({
+            let focused = focused.clone();
+            move || {
+                for event in events.flatten() {
+                    if let Some(accessible) = focused_by(&event) {
+                        *focused.lock().unwrap() = Some(accessible);
+                    }
+                }
+            }
+        });
+        Ok(Self {
+            connection,
+            focused,
+        })
+    }
+
+    /// Returns the extents of the character after the caret, in the focused object.
+    /// At the end of the text, the caret is after the last character.
+    pub fn caret_position(&self) -> Option<Rect> {
+        let text = self.focused(TEXT_INTERFACE)?;
+        let offset: i32 = text.get_property("CaretOffset").ok()?;
+        let extents = |offset: i32| -> Option<Rect> {
+            let (x, y, w, h): (i32, i32, i32, i32) = text
+                .call("GetCharacterExtents", &(offset, COORD_TYPE_SCREEN))
+                .ok()?;
+            (w > 0 || h > 0).then(|| Rect::new(x, y, w, h))
+        };
+        extents(offset).or_else(|| {
+            let previous = extents(offset.checked_sub(1)?)?;
+            Some(Rect {
+                left: previous.right,
+                ..previous
+            })
+        })
+    }
+
+    /// Returns the extents of the focused object.
+    pub fn focused_control(&self) -> Option<Rect> {
+        let component = self.focused(COMPONENT_INTERFACE)?;
+        let (x, y, w, h): (i32, i32, i32, i32) =
+            component.call("GetExtents", &(COORD_TYPE_SCREEN,)).ok()?;
+        (w > 0 && h > 0).then(|| Rect::new(x, y, w, h))
+    }
+
+    /// Returns an interface of the focused object.
+    fn focused(&self, interface: &'static str) -> Option<Proxy<'static>> {
+        let focused = self.focused.lock().unwrap().clone()?;
+        Proxy::new(
+            &self.connection,
+            focused.destination,
+            focused.path,
+            interface,
+        )
+        .ok()
+    }
+}
+
+/// Returns the object the event tells it has the focus, if it does.
+fn focused_by(event: &zbus::Message) -> Option<Accessible> {
+    let header = event.header().ok()?;
+    let member = header.member().ok()??;
+    // The events may have a last field, with the properties of the object.
+    let (kind, detail, _, _): (String, i32, i32, OwnedValue) = event.body_unchecked().ok()?;
+    let focused = match member.as_str() {
+        "StateChanged" => kind == "focused" && detail == 1,
+        "TextCaretMoved" => true,
+        _ => false,
+    };
+    if !focused {
+        return None;
+    }
+    Some(Accessible {
+        destination: header.sender().ok()??.to_string(),
+        path: header.path().ok()??.to_owned().into(),
+    })
+}
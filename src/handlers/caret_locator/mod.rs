@@ -0,0 +1,138 @@
+use slint::{ComponentHandle as _, WindowPosition};
+
+use crate::placement::{self, Anchors, Point};
+use crate::{handler::Handler, SharedApp};
+
+use super::{BeforeOpenHandler, NotifierReason};
+
+#[cfg(target_os = "linux")]
+mod atspi;
+#[cfg(target_os = "windows")]
+mod win32;
+#[cfg(target_os = "linux")]
+mod x11;
+
+impl From<Point> for WindowPosition {
+    fn from(val: Point) -> Self {
+        WindowPosition::Physical(slint::PhysicalPosition { x: val.x, y: val.y })
+    }
+}
+
+/// Places the window as the settings say: near the caret by default, else
+/// near the focused control, else at the mouse pointer, else at the center.
+/// When not opened by a shortcut (e.g. from the tray icon), no window is being
+/// typed in: the caret and the focused control are not looked for.
+pub fn get_handler<'a>() -> BeforeOpenHandler<'a> {
+    let locator = platform::CaretLocator::new();
+
+    Handler::new(move |args: &(SharedApp, NotifierReason)| {
+        let (app, reason) = args;
+
+        let typing = reason.is_shortcut();
+
+        let placements = app.settings().placement.clone();
+        let locator = locator.clone();
+        let _ = app.weak_ui().upgrade_in_event_loop(move |ui| {
+            let window = ui.window();
+            let position = window.position();
+            let anchors = Anchors {
+                caret: typing.then(|| locator.caret_position()).flatten(),
+                focused_control: typing.then(|| locator.focused_control()).flatten(),
+                mouse_pointer: platform::mouse_position(),
+                last_position: Some(Point {
+                    x: position.x,
+                    y: position.y,
+                }),
+            };
+            let window_size = platform::window_size(window);
+            let monitors = platform::monitors(window);
+            if let Some(position) =
+                placement::place_first(&placements, &anchors, window_size, &monitors)
+            {
+                window.set_position(position)
+            }
+        });
+    })
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use crate::placement::Rect;
+
+    pub use super::win32::{monitors, mouse_position, window_size};
+
+    /// The caret of the foreground window is asked to its accessible object.
+    /// The focused control is the focused child window.
+    #[derive(Clone)]
+    pub struct CaretLocator;
+
+    impl CaretLocator {
+        pub fn new() -> Self {
+            Self
+        }
+
+        pub fn caret_position(&self) -> Option<Rect> {
+            super::win32::caret_position()
+        }
+
+        pub fn focused_control(&self) -> Option<Rect> {
+            super::win32::focused_control()
+        }
+    }
+}
+
+/// On Linux, the caret is known through the accessibility bus, and the
+/// monitors and the pointer through X11. Wayland tells neither the position
+/// of the windows nor lets them choose it, so the caret is not looked for there.
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::sync::Arc;
+
+    use super::atspi::FocusTracker;
+    use crate::placement::{Monitor, Rect};
+
+    pub use super::x11::mouse_position;
+
+    #[derive(Clone)]
+    pub struct CaretLocator(Option<Arc<FocusTracker>>);
+
+    impl CaretLocator {
+        pub fn new() -> Self {
+            if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+                return Self(None);
+            }
+            match FocusTracker::start() {
+                Ok(tracker) => Self(Some(Arc::new(tracker))),
+                Err(e) => {
+                    eprintln!("Failed to locate the caret. Reason: {}", e);
+                    Self(None)
+                }
+            }
+        }
+
+        pub fn caret_position(&self) -> Option<Rect> {
+            self.0.as_ref()?.caret_position()
+        }
+
+        pub fn focused_control(&self) -> Option<Rect> {
+            self.0.as_ref()?.focused_control()
+        }
+    }
+
+    /// Returns the logical size of the window.
+    pub fn window_size(window: &slint::Window) -> (f32, f32) {
+        let size = window.size().to_logical(window.scale_factor());
+        (size.width, size.height)
+    }
+
+    /// X11 has a single scale for all the monitors, the one of the window.
+    pub fn monitors(window: &slint::Window) -> Vec<Monitor> {
+        super::x11::work_areas()
+            .into_iter()
+            .map(|work_area| Monitor {
+                work_area,
+                scale_factor: window.scale_factor(),
+            })
+            .collect()
+    }
+}
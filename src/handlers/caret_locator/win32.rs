@@ -0,0 +1,145 @@
+use std::ffi::c_void;
+
+use windows::{
+    core::{Interface, VARIANT},
+    Win32::{
+        Foundation::{BOOL, HWND, LPARAM, POINT, RECT, TRUE},
+        Graphics::Gdi::{EnumDisplayMonitors, GetMonitorInfoW, HDC, HMONITOR, MONITORINFO},
+        UI::{
+            Accessibility::{AccessibleObjectFromWindow, IAccessible},
+            HiDpi::{GetDpiForMonitor, MDT_EFFECTIVE_DPI},
+            WindowsAndMessaging::{
+                GetCursorPos, GetForegroundWindow, GetGUIThreadInfo, GetWindowRect,
+                GetWindowThreadProcessId, CHILDID_SELF, GUITHREADINFO, OBJID_CARET,
+                USER_DEFAULT_SCREEN_DPI,
+            },
+        },
+    },
+};
+
+use crate::handlers::utils::ToHWND;
+use crate::placement::{Monitor, Point, Rect};
+
+/// Returns the control having the keyboard focus, in the foreground window.
+fn focused_window() -> Option<HWND> {
+    unsafe {
+        let mut info: GUITHREADINFO = { std::mem::zeroed() };
+        info.cbSize = std::mem::size_of::<GUITHREADINFO>() as u32;
+
+        // Get thread of current active window
+        let hwnd = GetForegroundWindow();
+        let target_thread_id = if hwnd.is_invalid() {
+            0
+        } else {
+            GetWindowThreadProcessId(hwnd, None)
+        };
+        GetGUIThreadInfo(target_thread_id, &mut info as *mut _).ok()?;
+        (!info.hwndFocus.is_invalid()).then_some(info.hwndFocus)
+    }
+}
+
+pub fn caret_position() -> Option<Rect> {
+    let focused = focused_window()?;
+    unsafe {
+        // Get IAccessible interface
+        let mut if_ptr: *mut c_void = std::ptr::null_mut();
+        let guid = IAccessible::IID;
+        AccessibleObjectFromWindow(
+            focused,
+            OBJID_CARET.0 as u32,
+            &guid as *const _,
+            &mut if_ptr as *mut _,
+        )
+        .ok()?;
+        let acc_if: IAccessible = IAccessible::from_raw(if_ptr);
+
+        let (mut x, mut y, mut w, mut h) = (0, 0, 0, 0);
+        let variant = VARIANT::from(CHILDID_SELF as i32);
+        acc_if
+            .accLocation(
+                &mut x as *mut _,
+                &mut y as *mut _,
+                &mut w as *mut _,
+                &mut h as *mut _,
+                &variant,
+            )
+            .ok()?;
+
+        Some(Rect::new(x, y, w, h))
+    }
+}
+
+/// Returns the bounds of the focused control. Browsers and Electron
+/// applications have a single control for the whole page.
+pub fn focused_control() -> Option<Rect> {
+    let focused = focused_window()?;
+    let mut rect: RECT = unsafe { std::mem::zeroed() };
+    unsafe { GetWindowRect(focused, &mut rect as *mut _) }.ok()?;
+    Some(Rect {
+        left: rect.left,
+        top: rect.top,
+        right: rect.right,
+        bottom: rect.bottom,
+    })
+}
+
+/// Returns the logical size of the window with its frame, or of its client
+/// area if the frame is unknown.
+pub fn window_size(window: &slint::Window) -> (f32, f32) {
+    let frame = window.to_hwnd().and_then(|hwnd| {
+        let mut lprect: RECT = unsafe { std::mem::zeroed() };
+        unsafe { GetWindowRect(hwnd, &mut lprect as *mut _) }.ok()?;
+        Some(slint::PhysicalSize::new(
+            (lprect.right - lprect.left) as u32,
+            (lprect.bottom - lprect.top) as u32,
+        ))
+    });
+    let size = frame
+        .unwrap_or_else(|| window.size())
+        .to_logical(window.scale_factor());
+    (size.width, size.height)
+}
+
+pub fn mouse_position() -> Option<Point> {
+    let mut point = POINT::default();
+    unsafe { GetCursorPos(&mut point as *mut _) }.ok()?;
+    Some(Point {
+        x: point.x,
+        y: point.y,
+    })
+}
+
+/// Returns every monitor, with its own scale.
+pub fn monitors(_: &slint::Window) -> Vec<Monitor> {
+    unsafe extern "system" fn add(monitor: HMONITOR, _: HDC, _: *mut RECT, data: LPARAM) -> BOOL {
+        let monitors = &mut *(data.0 as *mut Vec<Monitor>);
+        let mut monitor_info: MONITORINFO = std::mem::zeroed();
+        monitor_info.cbSize = std::mem::size_of::<MONITORINFO>() as u32;
+        if GetMonitorInfoW(monitor, &mut monitor_info as *mut _).as_bool() {
+            let work_area = monitor_info.rcWork;
+            let (mut dpi, mut dpi_y) = (USER_DEFAULT_SCREEN_DPI, USER_DEFAULT_SCREEN_DPI);
+            let _ = GetDpiForMonitor(monitor, MDT_EFFECTIVE_DPI, &mut dpi, &mut dpi_y);
+            monitors.push(Monitor {
+                work_area: Rect {
+                    left: work_area.left,
+                    top: work_area.top,
+                    right: work_area.right,
+                    bottom: work_area.bottom,
+                },
+                scale_factor: dpi as f32 / USER_DEFAULT_SCREEN_DPI as f32,
+            });
+        }
+        TRUE
+    }
+
+    let mut monitors: Vec<Monitor> = Vec::new();
+    unsafe {
+        let _ = EnumDisplayMonitors(
+            HDC::default(),
+            None,
+            Some(add),
+            LPARAM(&mut monitors as *mut _ as isize),
+        );
+    }
+    monitors
+}
@@ -0,0 +1,115 @@
+use x11rb::{
+    connection::Connection,
+    protocol::{
+        randr::ConnectionExt as _,
+        xproto::{AtomEnum, ConnectionExt as _, Window},
+    },
+    rust_connection::RustConnection,
+};
+
+use crate::placement::{Point, Rect};
+
+x11rb::atom_manager! {
+    Atoms: AtomsCookie {
+        _NET_WORKAREA,
+        _NET_CURRENT_DESKTOP,
+    }
+}
+
+/// Returns the work area of each monitor: the part of it in the work area
+/// of the current desktop, as told by the window manager.
+/// The monitors are whole if the window manager does not tell it.
+pub fn work_areas() -> Vec<Rect> {
+    let Ok((connection, screen)) = x11rb::connect(None) else {
+        return Vec::new();
+    };
+    let desktop = desktop_work_area(&connection, connection.setup().roots[screen].root);
+    monitors(&connection, screen)
+        .into_iter()
+        .map(|monitor| {
+            desktop
+                .and_then(|desktop| intersection(monitor, desktop))
+                .unwrap_or(monitor)
+        })
+        .collect()
+}
+
+/// Returns the position of the mouse pointer.
+pub fn mouse_position() -> Option<Point> {
+    let (connection, screen) = x11rb::connect(None).ok()?;
+    let root = connection.setup().roots[screen].root;
+    let pointer = connection.query_pointer(root).ok()?.reply().ok()?;
+    Some(Point {
+        x: pointer.root_x as i32,
+        y: pointer.root_y as i32,
+    })
+}
+
+/// Returns the monitors of the screen. Without RandR 1.5, the screen is one monitor.
+fn monitors(connection: &RustConnection, screen: usize) -> Vec<Rect> {
+    let screen = &connection.setup().roots[screen];
+    let monitors = connection
+        .randr_get_monitors(screen.root, true)
+        .ok()
+        .and_then(|cookie| cookie.reply().ok());
+    match monitors {
+        Some(reply) if !reply.monitors.is_empty() => reply
+            .monitors
+            .iter()
+            .map(|monitor| {
+                Rect::new(
+                    monitor.x as i32,
+                    monitor.y as i32,
+                    monitor.width as i32,
+                    monitor.height as i32,
+                )
+            })
+            .collect(),
+        _ => vec![Rect::new(
+            0,
+            0,
+            screen.width_in_pixels as i32,
+            screen.height_in_pixels as i32,
+        )],
+    }
+}
+
+/// `_NET_WORKAREA` holds the work area of every desktop, spanning all the monitors.
+fn desktop_work_area(connection: &RustConnection, root: Window) -> Option<Rect> {
+    let atoms = Atoms::new(connection).ok()?.reply().ok()?;
+    let desktop = property32(connection, root, atoms._NET_CURRENT_DESKTOP, 0, 1)
+        .and_then(|values| values.first().copied())
+        .unwrap_or(0);
+    let values = property32(connection, root, atoms._NET_WORKAREA, desktop * 4, 4)?;
+    let [x, y, width, height] = values[..] else {
+        return None;
+    };
+    Some(Rect::new(x as i32, y as i32, width as i32, height as i32))
+}
+
+fn property32(
+    connection: &RustConnection,
+    window: Window,
+    property: u32,
+    offset: u32,
+    length: u32,
+) -> Option<Vec<u32>> {
+    let reply = connection
+        .get_property(false, window, property, AtomEnum::CARDINAL, offset, length)
+        .ok()?
+        .reply()
+        .ok()?;
+    let values = reply.value32()?.collect();
+    Some(values)
+}
+
+fn intersection(a: Rect, b: Rect) -> Option<Rect> {
+    let intersection = Rect {
+        left: a.left.max(b.left),
+        top: a.top.max(b.top),
+        right: a.right.min(b.right),
+        bottom: a.bottom.min(b.bottom),
+    };
+    (intersection.left < intersection.right && intersection.top < intersection.bottom)
+        .then_some(intersection)
+}
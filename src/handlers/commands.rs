@@ -0,0 +1,73 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    mpsc, Arc,
+};
+
+use slint::ComponentHandle as _;
+
+use crate::handler::{Handler, MpscNotifier};
+use crate::ipc::{Command, Listener};
+use crate::shortcut::OpenMode;
+use crate::{EmojiPickerWindow, SharedApp};
+
+use super::{
+    CloserNotifier, NotifierReason, NotifiersArgs, OnCloseHandler, OnOpenHandler, OpenerNotifier,
+};
+
+pub struct CommandHandlers<'a> {
+    pub on_open_handler: OnOpenHandler<'a>,
+    pub on_close_handler: OnCloseHandler<'a>,
+    pub opener: OpenerNotifier,
+    pub closer: CloserNotifier,
+}
+
+/// Opens and closes the picker on the commands of the other instances.
+/// The visibility of the window is tracked to toggle it.
+pub fn generate_handlers<'a>(ui: &EmojiPickerWindow, listener: Listener) -> CommandHandlers<'a> {
+    let (open_tx, open_rx) = mpsc::sync_channel::<NotifiersArgs>(1);
+    let (close_tx, close_rx) = mpsc::sync_channel::<NotifiersArgs>(1);
+    let visible = Arc::new(AtomicBool::new(false));
+
+    listener.serve({
+        let (ui, visible) = (ui.as_weak(), visible.clone());
+        move |command| {
+            // The commands are run by key bindings of the window manager,
+            // so they open the picker like a shortcut.
+            // The picker may already be opening or closing, the command is dropped then
+            // instead of blocking the listener.
+            let _ = match command {
+                Command::Show { group, query } => {
+                    if let Some(query) = query {
+                        let _ =
+                            ui.upgrade_in_event_loop(move |ui| ui.invoke_set_filter(query.into()));
+                    }
+                    open_tx.try_send(NotifierReason::Shortcut(group.into()))
+                }
+                Command::Hide => close_tx.try_send(NotifierReason::None),
+                Command::Toggle if visible.load(Ordering::Acquire) => {
+                    close_tx.try_send(NotifierReason::None)
+                }
+                Command::Toggle => open_tx.try_send(NotifierReason::Shortcut(OpenMode::Default)),
+                Command::Quit => close_tx.try_send(NotifierReason::Quit),
+                Command::ReloadConfig => {
+                    crate::settings::request_reload();
+                    Ok(())
+                }
+            };
+        }
+    });
+
+    let on_open_handler = Handler::new({
+        let visible = visible.clone();
+        move |_: &(SharedApp, _)| visible.store(true, Ordering::Release)
+    });
+    let on_close_handler =
+        Handler::new(move |_: &(SharedApp, _)| visible.store(false, Ordering::Release));
+
+    CommandHandlers {
+        on_open_handler,
+        on_close_handler,
+        opener: Box::new(MpscNotifier::new(open_rx)),
+        closer: Box::new(MpscNotifier::new(close_rx)),
+    }
+}
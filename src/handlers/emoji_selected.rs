@@ -1,115 +1,344 @@
-use windows::Win32::UI::{
-    Input::KeyboardAndMouse::{
-        SendInput, INPUT, INPUT_0, INPUT_KEYBOARD, KEYBDINPUT, KEYEVENTF_KEYUP, KEYEVENTF_UNICODE,
-        VIRTUAL_KEY,
-    },
-    WindowsAndMessaging::GetMessageExtraInfo,
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+#[cfg(target_os = "linux")]
+use std::time::{Duration, Instant};
+
+#[cfg(target_os = "linux")]
+use slint::ComponentHandle as _;
+
+use crate::output::{
+    ClipboardSink, FallbackSink, FileSink, OutputSink, PasteSink, StdoutSink, TypeSink,
 };
+use crate::rules::{self, Output};
+use crate::settings::{Insertion, OutputMode, Settings};
+use crate::window_info::{WindowInfo, WindowInfoProvider};
+use crate::{handler::Handler, RunMode, SharedApp};
+
+use super::{BeforeOpenHandler, EmojiSelectedHandler, NotifierReason};
 
-use crate::{handler::Handler, SharedApp};
+/// How long the focus has to come back to the window the emoji goes to, once the picker is hidden.
+#[cfg(target_os = "linux")]
+const FOCUS_TIMEOUT: Duration = Duration::from_secs(1);
+/// Interval between two checks of the focused window.
+#[cfg(target_os = "linux")]
+const FOCUS_POLL_INTERVAL: Duration = Duration::from_millis(20);
+/// Time given to the window manager to move the focus, when the focused window is unknown.
+#[cfg(target_os = "linux")]
+const FOCUS_DELAY: Duration = Duration::from_millis(200);
+
+pub struct EmojiSelectedHandlers<'a> {
+    pub before_open_handler: BeforeOpenHandler<'a>,
+    pub emoji_selected_handler: EmojiSelectedHandler<'a>,
+}
 
-use super::EmojiSelectedHandler;
+/// The emoji picker sends the requested String to where the settings say,
+/// the rules matching the window focused before the picker opened included.
+/// When run to print the emoji, it is written to the standard output instead, then the picker quits.
+/// On Linux, the picker closes before the emoji is typed or pasted into another window.
+pub fn generate_handlers<'a>(
+    windows: impl WindowInfoProvider + 'static,
+) -> EmojiSelectedHandlers<'a> {
+    let windows = Arc::new(windows);
+    let target = Arc::new(Mutex::new(None::<WindowInfo>));
 
-// On a Windows system, the emoji picker will send the requested String to the active window.
-pub fn get_handler<'a>() -> EmojiSelectedHandler<'a> {
-    Handler::new(|args: &(SharedApp, String)| {
+    let before_open_handler = Handler::new({
+        let (windows, target) = (windows.clone(), target.clone());
+        move |_: &(SharedApp, _)| *target.lock().unwrap() = windows.foreground()
+    });
+
+    let emoji_selected_handler = Handler::new(move |args: &(SharedApp, String)| {
         let (app, code) = args;
 
-        if cfg!(target_os = "windows") && app.get_reason().should_type_emoji() {
-            type_emoji(code);
-        } else {
-            clipboard(code);
+        let settings = app.settings();
+        let target = target.lock().unwrap().clone();
+        let output = rules::output_for(&settings, target.as_ref());
+        let sink = select_sink(app.run_mode(), app.get_reason(), output, &settings);
+        let emoji = match app.run_mode() {
+            RunMode::Print => code.clone(),
+            _ => output.format.apply(code),
+        };
+        drop(settings);
+
+        // The picker has the focus on Linux: it gets out of the way, and the keys
+        // are sent once the window they go to has the focus back.
+        #[cfg(target_os = "linux")]
+        if sink.needs_focus() {
+            if let Some(ui) = app.weak_ui().upgrade() {
+                let _ = ui.window().hide();
+                ui.invoke_close_requested();
+            }
+            let windows = windows.clone();
+            std::thread::spawn(move || {
+                wait_for_focus(&*windows, target.as_ref());
+                if let Err(e) = sink.output(&emoji) {
+                    eprintln!("{}", e);
+                }
+            });
+            return;
         }
-    })
-}
 
-/// Types the given String as Unicode characters.
-#[cfg(target_os = "windows")]
-pub fn type_emoji(code: &str) {
-    let encoded = str::encode_utf16(code);
-    let extra_info = unsafe { GetMessageExtraInfo() };
-    let input_struct_kd = encoded.into_iter().map(|c| INPUT {
-        r#type: INPUT_KEYBOARD,
-        Anonymous: INPUT_0 {
-            ki: KEYBDINPUT {
-                dwExtraInfo: extra_info.0 as usize,
-                wVk: VIRTUAL_KEY(0),
-                wScan: c,
-                dwFlags: KEYEVENTF_UNICODE,
-                time: 0,
-            },
-        },
-    });
-    let input_struct_kf = input_struct_kd.clone().map(|mut k| {
-        unsafe {
-            k.Anonymous.ki.dwFlags |= KEYEVENTF_KEYUP;
+        let result = sink.output(&emoji);
+        if let Err(e) = &result {
+            eprintln!("{}", e);
+        }
+
+        if app.run_mode() == RunMode::Print {
+            if result.is_ok() {
+                app.set_printed();
+            }
+            let _ = slint::quit_event_loop();
         }
-        k
     });
-    let input_struct = input_struct_kd.chain(input_struct_kf).collect::<Vec<_>>();
-    unsafe {
-        SendInput(input_struct.as_slice(), std::mem::size_of::<INPUT>() as i32);
+
+    EmojiSelectedHandlers {
+        before_open_handler,
+        emoji_selected_handler,
     }
 }
 
-/// Copies the string to the clipboard.
-#[cfg(target_os = "windows")]
-pub fn clipboard(code: &str) {
-    use windows::Win32::{
-        Foundation::{GlobalFree, HANDLE, NO_ERROR},
-        System::{
-            DataExchange::{CloseClipboard, EmptyClipboard, OpenClipboard, SetClipboardData},
-            Memory::{GlobalAlloc, GlobalLock, GlobalUnlock, GMEM_MOVEABLE},
-            Ole::CF_UNICODETEXT,
-        },
+/// Waits for the window the emoji goes to to get the focus, for a while.
+/// Another window of the same application is not enough: it is told apart by its id.
+/// When it is unknown (on Wayland), the window manager is only given some time.
+#[cfg(target_os = "linux")]
+fn wait_for_focus(windows: &dyn WindowInfoProvider, target: Option<&WindowInfo>) {
+    let Some(target) = target else {
+        std::thread::sleep(FOCUS_DELAY);
+        return;
     };
+    let deadline = Instant::now() + FOCUS_TIMEOUT;
+    while windows.foreground().map(|window| window.id) != Some(target.id)
+        && Instant::now() < deadline
+    {
+        std::thread::sleep(FOCUS_POLL_INTERVAL);
+    }
+}
 
-    let utf16: Vec<u16> = str::encode_utf16(code)
-        .chain(std::iter::once(0u16))
-        .collect();
-    let len = utf16.len();
-    unsafe {
-        if OpenClipboard(None).is_err() {
-            eprintln!("Failed to open clipboard");
-            return;
-        };
-        if EmptyClipboard().is_err() {
-            eprint!("Failed to empty clipboard");
-            let _ = CloseClipboard();
+/// Where the selected emoji goes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Destination {
+    Stdout,
+    Clipboard,
+    /// Inserted in the focused window.
+    Insert(Insertion),
+    /// Inserted in the focused window, or copied if it cannot be.
+    InsertOrCopy(Insertion),
+    File(PathBuf),
+}
+
+impl Destination {
+    fn sink(self) -> Box<dyn OutputSink> {
+        let insert = |insertion| -> Box<dyn OutputSink> {
+            match insertion {
+                Insertion::Type => Box::new(TypeSink),
+                Insertion::Paste => Box::new(PasteSink),
+            }
         };
-        let global = GlobalAlloc(GMEM_MOVEABLE, len * std::mem::size_of::<u16>());
+        match self {
+            Destination::Stdout => Box::new(StdoutSink),
+            Destination::Clipboard => Box::new(ClipboardSink),
+            Destination::Insert(insertion) => insert(insertion),
+            Destination::InsertOrCopy(insertion) => {
+                Box::new(FallbackSink(insert(insertion), ClipboardSink))
+            }
+            Destination::File(path) => Box::new(FileSink(path)),
+        }
+    }
+}
 
-        if global.is_err() {
-            eprintln!("Failed to allocate memory");
-            let _ = CloseClipboard();
-            return;
+/// Returns where the emoji goes, depending on how the picker runs and was opened.
+/// Typing only makes sense if a window had the focus when the picker opened
+/// (i.e. it was opened by a shortcut), so the emoji is copied otherwise.
+fn select_sink(
+    run_mode: RunMode,
+    reason: NotifierReason,
+    output: Output,
+    settings: &Settings,
+) -> Box<dyn OutputSink> {
+    destination(run_mode, reason, output, settings).sink()
+}
+
+fn destination(
+    run_mode: RunMode,
+    reason: NotifierReason,
+    output: Output,
+    settings: &Settings,
+) -> Destination {
+    if run_mode == RunMode::Print {
+        return Destination::Stdout;
+    }
+    match output.mode {
+        OutputMode::Auto if reason.should_type_emoji() => {
+            Destination::InsertOrCopy(output.insertion)
+        }
+        OutputMode::Auto | OutputMode::Clipboard => Destination::Clipboard,
+        OutputMode::Type => Destination::Insert(output.insertion),
+        OutputMode::TypeOrClipboard => Destination::InsertOrCopy(output.insertion),
+        // The settings are validated: the file is set.
+        OutputMode::File => match &settings.output_file {
+            Some(path) => Destination::File(path.clone()),
+            None => Destination::Clipboard,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::EmojiFormat;
+    use crate::shortcut::OpenMode;
+
+    const SHORTCUT: NotifierReason = NotifierReason::Shortcut(OpenMode::Default);
+
+    fn output(mode: OutputMode, insertion: Insertion) -> Output {
+        Output {
+            mode,
+            insertion,
+            format: EmojiFormat::Emoji,
         }
+    }
 
-        let global = global.unwrap();
+    fn settings() -> Settings {
+        Settings {
+            output_file: Some(PathBuf::from("emojis.txt")),
+            ..Settings::default()
+        }
+    }
 
-        let addr: *mut _ = GlobalLock(global);
-        if addr.is_null() {
-            eprintln!("Failed to lock memory");
-            let _ = GlobalFree(global);
-            let _ = CloseClipboard();
-            return;
+    fn selected(run_mode: RunMode, reason: NotifierReason, output: Output) -> Destination {
+        destination(run_mode, reason, output, &settings())
+    }
+
+    #[test]
+    fn prints_in_the_print_mode() {
+        for mode in [OutputMode::Auto, OutputMode::Type, OutputMode::File] {
+            let output = output(mode, Insertion::Type);
+            assert_eq!(
+                selected(RunMode::Print, SHORTCUT, output),
+                Destination::Stdout
+            );
         }
+    }
 
-        let slice = std::slice::from_raw_parts_mut(addr as *mut u16, len);
-        slice.copy_from_slice(&utf16);
-        let unlock_result = GlobalUnlock(global); // This… when… completes… successfully… returns… an… Err…?
-        if unlock_result.is_ok() || unlock_result.unwrap_err().code() != NO_ERROR.into() {
-            eprintln!("Failed to unlock memory");
-            let _ = GlobalFree(global);
-            let _ = CloseClipboard();
-            return;
+    #[test]
+    fn types_automatically_only_after_a_shortcut() {
+        let auto = output(OutputMode::Auto, Insertion::Type);
+        assert_eq!(
+            selected(RunMode::Background, SHORTCUT, auto),
+            Destination::InsertOrCopy(Insertion::Type)
+        );
+        let auto_paste = output(OutputMode::Auto, Insertion::Paste);
+        assert_eq!(
+            selected(RunMode::Open, SHORTCUT, auto_paste),
+            Destination::InsertOrCopy(Insertion::Paste)
+        );
+        for reason in [NotifierReason::None, NotifierReason::TrayIcon] {
+            assert_eq!(
+                selected(RunMode::Background, reason, auto),
+                Destination::Clipboard
+            );
         }
-        if SetClipboardData(CF_UNICODETEXT.0.into(), HANDLE(global.0)).is_err() {
-            eprint!("Failed to set clipboard data");
-            let _ = GlobalFree(global);
-            let _ = CloseClipboard();
-            return;
+    }
+
+    #[test]
+    fn follows_the_explicit_modes_whatever_the_reason() {
+        let cases = [
+            (OutputMode::Clipboard, Destination::Clipboard),
+            (OutputMode::Type, Destination::Insert(Insertion::Paste)),
+            (
+                OutputMode::TypeOrClipboard,
+                Destination::InsertOrCopy(Insertion::Paste),
+            ),
+            (
+                OutputMode::File,
+                Destination::File(PathBuf::from("emojis.txt")),
+            ),
+        ];
+        for (mode, destination) in cases {
+            for reason in [SHORTCUT, NotifierReason::None] {
+                let output = output(mode, Insertion::Paste);
+                assert_eq!(selected(RunMode::Background, reason, output), destination);
+            }
+        }
+    }
+
+    #[test]
+    fn only_the_insertions_need_the_focus() {
+        let settings = settings();
+        let needs_focus = |mode, insertion| {
+            select_sink(
+                RunMode::Background,
+                SHORTCUT,
+                output(mode, insertion),
+                &settings,
+            )
+            .needs_focus()
         };
-        let _ = CloseClipboard();
-    };
+        assert!(needs_focus(OutputMode::Type, Insertion::Type));
+        assert!(needs_focus(OutputMode::Auto, Insertion::Paste));
+        assert!(needs_focus(OutputMode::TypeOrClipboard, Insertion::Type));
+        assert!(!needs_focus(OutputMode::Clipboard, Insertion::Paste));
+        assert!(!needs_focus(OutputMode::File, Insertion::Type));
+        let print = output(OutputMode::Type, Insertion::Type);
+        assert!(!select_sink(RunMode::Print, SHORTCUT, print, &settings).needs_focus());
+    }
+
+    #[test]
+    fn appends_to_the_output_file() {
+        let path = std::env::temp_dir().join(format!("emoji-picker-output-{}", std::process::id()));
+        let settings = Settings {
+            output: OutputMode::File,
+            output_file: Some(path.clone()),
+            ..Settings::default()
+        };
+        let output = output(OutputMode::File, Insertion::Type);
+        let sink = select_sink(RunMode::Background, SHORTCUT, output, &settings);
+        sink.output("👍").unwrap();
+        sink.output("🎉").unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(content, "👍\n🎉\n");
+    }
+
+    /// Tells the windows in turn, then the last one.
+    #[cfg(target_os = "linux")]
+    struct FocusedWindows(Mutex<Vec<WindowInfo>>);
+
+    #[cfg(target_os = "linux")]
+    impl WindowInfoProvider for FocusedWindows {
+        fn foreground(&self) -> Option<WindowInfo> {
+            let mut windows = self.0.lock().unwrap();
+            match windows.len() {
+                0 => None,
+                1 => windows.first().cloned(),
+                _ => Some(windows.remove(0)),
+            }
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn waits_for_the_target_window_itself() {
+        let editor = |id| WindowInfo {
+            id,
+            process: Some("gedit".to_owned()),
+            class: Some("Gedit".to_owned()),
+        };
+        let (picker, other, target) = (editor(1), editor(2), editor(3));
+        let windows = FocusedWindows(Mutex::new(vec![picker, other.clone(), target.clone()]));
+        wait_for_focus(&windows, Some(&target));
+        assert_eq!(*windows.0.lock().unwrap(), [target]);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn gives_up_waiting_for_the_focus() {
+        let window = |id| WindowInfo {
+            id,
+            ..WindowInfo::default()
+        };
+        let windows = FocusedWindows(Mutex::new(vec![window(2)]));
+        let start = Instant::now();
+        wait_for_focus(&windows, Some(&window(3)));
+        assert!(start.elapsed() >= FOCUS_TIMEOUT);
+    }
 }
@@ -0,0 +1,26 @@
+use slint::Model as _;
+
+use crate::emoji::EmojiWrapper;
+use crate::emoji_model::VecEmojiGroupModel;
+use crate::{handler::Handler, SharedApp};
+
+use super::BeforeOpenHandler;
+
+/// Reads the favorites again before the window opens, so that the changes
+/// made by hand to the favorites file are displayed.
+pub fn get_before_open_handler<'a>() -> BeforeOpenHandler<'a> {
+    Handler::new(|args: &(SharedApp, _)| {
+        let (app, _) = args;
+        let favorites: Vec<EmojiWrapper> = {
+            let mut favorites = app.favorites();
+            favorites.reload();
+            favorites.emojis().collect()
+        };
+        let _ = app.weak_ui().upgrade_in_event_loop(move |ui| {
+            let groups = ui.get_emoji_groups();
+            if let Some(model) = groups.as_any().downcast_ref::<VecEmojiGroupModel>() {
+                model.set_favorites(favorites);
+            }
+        });
+    })
+}
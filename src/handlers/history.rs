@@ -0,0 +1,34 @@
+use slint::Model as _;
+
+use crate::emoji::EmojiWrapper;
+use crate::emoji_model::VecEmojiGroupModel;
+use crate::{handler::Handler, SharedApp};
+
+use super::{BeforeOpenHandler, EmojiSelectedHandler};
+
+/// Records the selected emoji in the history and saves it.
+pub fn get_emoji_selected_handler<'a>() -> EmojiSelectedHandler<'a> {
+    Handler::new(|args: &(SharedApp, String)| {
+        let (app, code) = args;
+        let mut history = app.history();
+        history.record(code);
+        if let Err(e) = history.save() {
+            eprintln!("Failed to save the emoji history. Reason: {}", e);
+        }
+    })
+}
+
+/// Refreshes the "Recently used" group before the window opens.
+/// It is not refreshed on selection, so that emojis do not move under the cursor.
+pub fn get_before_open_handler<'a>() -> BeforeOpenHandler<'a> {
+    Handler::new(|args: &(SharedApp, _)| {
+        let (app, _) = args;
+        let recent: Vec<EmojiWrapper> = app.history().emojis().collect();
+        let _ = app.weak_ui().upgrade_in_event_loop(move |ui| {
+            let groups = ui.get_emoji_groups();
+            if let Some(model) = groups.as_any().downcast_ref::<VecEmojiGroupModel>() {
+                model.set_recent(recent);
+            }
+        });
+    })
+}
@@ -5,7 +5,7 @@ use crate::handler::Handler;
 use crate::SharedApp;
 
 use super::utils::ToHWND;
-use super::{NotifierReason, NotifiersArgs, OnCloseHandler, OnOpenHandler};
+use super::{NotifiersArgs, OnCloseHandler, OnOpenHandler};
 
 mod emoji_picker_hooker {
     // Linking DLLs on Windows with Rust is a damn shame.
@@ -16,12 +16,15 @@ mod emoji_picker_hooker {
     }
 }
 
+/// The hook posts the keys typed in the other window to the picker as WM_KEYDOWN and WM_KEYUP.
+/// Winit turns them into key events like the ones typed in the picker, so the filter gets
+/// the text and the arrows move the highlight the same way.
 pub fn get_open_handler<'a>() -> OnOpenHandler<'a> {
     Handler::new(|app: &(SharedApp, NotifiersArgs)| {
         let (app, reason) = app;
 
         // This is a shortcut related behavior.
-        if *reason != NotifierReason::Shortcut {
+        if !reason.is_shortcut() {
             return;
         }
 
@@ -6,15 +6,22 @@ use std::sync::{
 use windows::Win32::{
     Foundation::{HMODULE, LPARAM, LRESULT, WPARAM},
     UI::{
-        Input::KeyboardAndMouse::{GetAsyncKeyState, VK_LWIN, VK_OEM_PERIOD, VK_RWIN},
+        Input::KeyboardAndMouse::{
+            GetAsyncKeyState, VIRTUAL_KEY, VK_BACK, VK_CONTROL, VK_DELETE, VK_DOWN, VK_END,
+            VK_ESCAPE, VK_F1, VK_F24, VK_HOME, VK_INSERT, VK_LEFT, VK_LWIN, VK_MENU, VK_NEXT,
+            VK_OEM_1, VK_OEM_2, VK_OEM_3, VK_OEM_4, VK_OEM_5, VK_OEM_6, VK_OEM_7, VK_OEM_COMMA,
+            VK_OEM_MINUS, VK_OEM_PERIOD, VK_OEM_PLUS, VK_PRIOR, VK_RETURN, VK_RIGHT, VK_RWIN,
+            VK_SHIFT, VK_SPACE, VK_TAB, VK_UP,
+        },
         WindowsAndMessaging::{
             CallNextHookEx, SetWindowsHookExA, UnhookWindowsHookEx, HHOOK, KBDLLHOOKSTRUCT,
-            WH_KEYBOARD_LL, WM_KEYDOWN,
+            WH_KEYBOARD_LL, WM_KEYDOWN, WM_SYSKEYDOWN,
         },
     },
 };
 
 use crate::handler::Notifier;
+use crate::shortcut::{self, Key, Modifiers};
 
 use super::{NotifierReason, NotifiersArgs};
 
@@ -23,16 +30,17 @@ static mut HOOK_CHANNEL: Option<SyncSender<NotifiersArgs>> = None;
 unsafe extern "system" fn keyboard_hook(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
     let kbd: KBDLLHOOKSTRUCT = unsafe { *(lparam.0 as *const KBDLLHOOKSTRUCT) };
 
-    let windows_key_pressed = (GetAsyncKeyState(VK_LWIN.0.into()) >> 15) & 1 == 1
-        || (GetAsyncKeyState(VK_RWIN.0.into()) >> 15) & 1 == 1;
+    // With Alt held, the key presses are "system" ones.
+    let is_key_down = wparam.0 == WM_KEYDOWN as usize || wparam.0 == WM_SYSKEYDOWN as usize;
+    let mode = key_from_vk(kbd.vkCode)
+        .filter(|_| is_key_down)
+        .and_then(|key| shortcut::find_current(held_modifiers(), key));
 
-    if windows_key_pressed
-        && wparam.0 == WM_KEYDOWN as usize
-        && kbd.vkCode == VK_OEM_PERIOD.0.into()
-    {
+    if let Some(mode) = mode {
         let tx = unsafe { HOOK_CHANNEL.as_ref() };
         if let Some(tx) = tx {
-            tx.send(NotifierReason::Shortcut).unwrap();
+            // The picker may already be opening.
+            let _ = tx.try_send(NotifierReason::Shortcut(mode));
         }
         return LRESULT(1);
     }
@@ -41,6 +49,61 @@ unsafe extern "system" fn keyboard_hook(code: i32, wparam: WPARAM, lparam: LPARA
     CallNextHookEx(HHOOK::default(), code, wparam, lparam)
 }
 
+fn is_held(key: VIRTUAL_KEY) -> bool {
+    (unsafe { GetAsyncKeyState(key.0.into()) } >> 15) & 1 == 1
+}
+
+fn held_modifiers() -> Modifiers {
+    Modifiers {
+        ctrl: is_held(VK_CONTROL),
+        alt: is_held(VK_MENU),
+        shift: is_held(VK_SHIFT),
+        logo: is_held(VK_LWIN) || is_held(VK_RWIN),
+    }
+}
+
+/// Converts a virtual-key code to a key that can be used in a shortcut.
+/// The punctuation marks are the ones of a US keyboard.
+fn key_from_vk(vk: u32) -> Option<Key> {
+    let vk = VIRTUAL_KEY(u16::try_from(vk).ok()?);
+    let key = match vk {
+        VIRTUAL_KEY(code @ (0x30..=0x39 | 0x41..=0x5A)) => {
+            Key::Char(char::from(code as u8).to_ascii_lowercase())
+        }
+        VIRTUAL_KEY(code) if (VK_F1.0..=VK_F24.0).contains(&code) => {
+            Key::F((code - VK_F1.0 + 1) as u8)
+        }
+        VK_SPACE => Key::Space,
+        VK_RETURN => Key::Enter,
+        VK_TAB => Key::Tab,
+        VK_ESCAPE => Key::Escape,
+        VK_BACK => Key::Backspace,
+        VK_INSERT => Key::Insert,
+        VK_DELETE => Key::Delete,
+        VK_HOME => Key::Home,
+        VK_END => Key::End,
+        VK_PRIOR => Key::PageUp,
+        VK_NEXT => Key::PageDown,
+        VK_UP => Key::Up,
+        VK_DOWN => Key::Down,
+        VK_LEFT => Key::Left,
+        VK_RIGHT => Key::Right,
+        VK_OEM_PERIOD => Key::Char('.'),
+        VK_OEM_COMMA => Key::Char(','),
+        VK_OEM_PLUS => Key::Char('='),
+        VK_OEM_MINUS => Key::Char('-'),
+        VK_OEM_1 => Key::Char(';'),
+        VK_OEM_2 => Key::Char('/'),
+        VK_OEM_3 => Key::Char('`'),
+        VK_OEM_4 => Key::Char('['),
+        VK_OEM_5 => Key::Char('\\'),
+        VK_OEM_6 => Key::Char(']'),
+        VK_OEM_7 => Key::Char('\''),
+        _ => return None,
+    };
+    Some(key)
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum KeyShortcutError {
     #[error("Failed to create a hook. Reason: {0}")]
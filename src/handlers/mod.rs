@@ -1,14 +1,25 @@
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, RwLock};
+use std::time::Duration;
 
 use crate::handler::{Handler, Notifier, OnceNotifier};
+use crate::ipc::Listener;
 use crate::poller::Poller;
-use crate::{EmojiPickerWindow, SharedApp};
-
+use crate::settings::{Features, Settings};
+use crate::shortcut::OpenMode;
+use crate::window_info::SystemWindows;
+use crate::{EmojiPickerWindow, RunMode, SharedApp};
+
+mod commands;
+mod favorites;
+mod history;
 mod on_close_slint;
 mod on_open_slint;
+mod open_mode;
+mod state;
 
 #[cfg(feature = "caret")]
-#[cfg(target_os = "windows")]
+#[cfg(any(target_os = "windows", target_os = "linux"))]
 mod caret_locator;
 
 #[cfg(feature = "no-activate")]
@@ -19,11 +30,18 @@ mod no_activate;
 #[cfg(target_os = "windows")]
 mod key_shortcut;
 
+#[cfg(feature = "key-shortcut")]
+#[cfg(target_os = "linux")]
+mod portal_shortcut;
+
+#[cfg(feature = "key-shortcut")]
+#[cfg(target_os = "linux")]
+mod x11_shortcut;
+
 #[cfg(feature = "key-redir")]
 #[cfg(target_os = "windows")]
 mod key_redir;
 
-#[cfg(target_os = "windows")]
 mod emoji_selected;
 
 #[cfg(feature = "tray-icon")]
@@ -33,6 +51,7 @@ mod tray_icon;
 #[cfg(target_os = "windows")]
 mod back_click;
 
+#[cfg(target_os = "windows")]
 mod utils;
 
 /// Defines the reason for the notifier to be called
@@ -44,16 +63,30 @@ pub enum NotifierReason {
     /// This is useful for open notifiers that can signal
     /// whether the window was opened by a shortcut (e.g. not showing
     /// the back-click for a tray icon activation.)
-    Shortcut,
+    /// Each shortcut has its own way of opening the picker.
+    Shortcut(OpenMode),
     /// Used by opening the window from the tray icon
     TrayIcon,
     /// If the close notifier is a back-click
     Backclick,
+    /// If the application must exit once the window is closed
+    Quit,
 }
 
 impl NotifierReason {
+    pub fn is_shortcut(&self) -> bool {
+        matches!(self, NotifierReason::Shortcut(_))
+    }
+
     pub fn should_type_emoji(&self) -> bool {
-        matches!(self, NotifierReason::Shortcut)
+        self.is_shortcut()
+    }
+
+    /// Whether the window closes once an emoji is accepted with the keyboard:
+    /// when opened to type in another window, the picker gets out of the way.
+    /// Otherwise, more emojis may be picked.
+    pub fn closes_on_accept(&self) -> bool {
+        self.is_shortcut()
     }
 }
 
@@ -76,6 +109,10 @@ pub struct Handlers<'a> {
     pub on_close_handlers: Vec<OnCloseHandler<'a>>,
     pub before_open_handlers: Vec<BeforeOpenHandler<'a>>,
     pub on_open_handlers: Vec<OnOpenHandler<'a>>,
+    /// Interval between two checks of the notifiers, in milliseconds.
+    poll_interval_ms: Arc<AtomicU64>,
+    /// The features turned on by the settings, checked by their handlers.
+    features: Arc<RwLock<Features>>,
 }
 
 /// Represents the different events that can be triggered from the UI.
@@ -93,9 +130,23 @@ pub enum HandlerNotifyEvent {
 }
 
 impl<'a> Handlers<'a> {
-    /// Generates a default set of handlers.
-    pub fn new(ui: &EmojiPickerWindow) -> Self {
-        get_handlers(ui)
+    /// Generates the set of handlers enabled by the settings.
+    /// The listener, if any, receives the commands of the other instances.
+    pub fn new(
+        ui: &EmojiPickerWindow,
+        settings: &Settings,
+        listener: Option<Listener>,
+        run_mode: RunMode,
+    ) -> Self {
+        get_handlers(ui, settings, listener, run_mode)
+    }
+
+    /// Applies the settings read by the handlers themselves: the interval between
+    /// two checks of the notifiers, and the features that are turned on.
+    pub fn apply_settings(&self, settings: &Settings) {
+        self.poll_interval_ms
+            .store(settings.poll_interval_ms, Ordering::Relaxed);
+        *self.features.write().unwrap() = settings.features.clone();
     }
 
     /// Triggers the event.
@@ -133,7 +184,9 @@ impl<'a> Handlers<'a> {
         };
 
         let notifiers = notifiers.clone();
-        Poller::new(move || {
+        let poll_interval_ms = self.poll_interval_ms.clone();
+        let interval = move || Duration::from_millis(poll_interval_ms.load(Ordering::Relaxed));
+        Poller::with_interval(interval, move || {
             let callback = callback.read().unwrap();
             for notifier in notifiers.iter() {
                 if let Some(t) = notifier.has_notified() {
@@ -144,28 +197,81 @@ impl<'a> Handlers<'a> {
     }
 }
 
+/// Calls the handler only while the feature is turned on by the settings,
+/// so that it can be turned on and off without restarting the picker.
+#[allow(dead_code)] // Only used by the features
+fn gated<'a>(
+    features: &Arc<RwLock<Features>>,
+    feature: fn(&Features) -> bool,
+    handler: GeneralAppHandler<'a>,
+) -> GeneralAppHandler<'a> {
+    let features = features.clone();
+    Handler::new(move |args: &HandlerArgs| {
+        if feature(&features.read().unwrap()) {
+            handler.call(args);
+        }
+    })
+}
+
 /// Generates the handlers for the UI.
-fn get_handlers<'a>(ui: &EmojiPickerWindow) -> Handlers<'a> {
+/// The features compiled in can still be disabled by the settings.
+fn get_handlers<'a>(
+    ui: &EmojiPickerWindow,
+    settings: &Settings,
+    listener: Option<Listener>,
+    run_mode: RunMode,
+) -> Handlers<'a> {
     let mut emoji_selected: Vec<EmojiSelectedHandler> = vec![];
     let mut openers: Vec<OpenerNotifier> = vec![];
     let mut closers: Vec<CloserNotifier> = vec![];
     let mut on_close_handlers: Vec<OnCloseHandler> = vec![];
     let mut on_open_handlers: Vec<OnOpenHandler> = vec![];
     let mut before_open_handlers: Vec<BeforeOpenHandler> = vec![];
+    let features = Arc::new(RwLock::new(settings.features.clone()));
 
-    // Open the window on startup
-    openers.push(Box::new(OnceNotifier::new(NotifierReason::None)));
+    // Open the window on startup, unless running in the background
+    if run_mode != RunMode::Background {
+        openers.push(Box::new(OnceNotifier::new(NotifierReason::None)));
+    }
+    // A printing picker exits once closed, so nothing opens it again.
+    #[allow(unused_variables)] // Only used by the shortcuts and the tray icon
+    let reopens = run_mode != RunMode::Print;
 
     // Slint dependant
     closers.push(on_close_slint::get_close_shortcut_notifier(ui));
     before_open_handlers.push(on_open_slint::get_handler());
 
-    emoji_selected.push(emoji_selected::get_handler());
+    // The emoji goes to the window focused before the picker opened.
+    let emoji_selected_handlers = emoji_selected::generate_handlers(SystemWindows);
+    before_open_handlers.push(emoji_selected_handlers.before_open_handler);
+    emoji_selected.push(emoji_selected_handlers.emoji_selected_handler);
+
+    // Recently used emojis
+    emoji_selected.push(history::get_emoji_selected_handler());
+    before_open_handlers.push(history::get_before_open_handler());
+
+    // Favorites
+    before_open_handlers.push(favorites::get_before_open_handler());
+
+    // Shortcuts opening into a group
+    on_open_handlers.push(open_mode::get_handler());
+
+    // Skin tone and window state
+    on_close_handlers.push(state::get_close_handler());
+
+    // Commands of the other instances
+    if let Some(listener) = listener {
+        let command_handlers = commands::generate_handlers(ui, listener);
+        on_open_handlers.push(command_handlers.on_open_handler);
+        on_close_handlers.push(command_handlers.on_close_handler);
+        openers.push(command_handlers.opener);
+        closers.push(command_handlers.closer);
+    }
 
     #[cfg(feature = "caret")]
-    #[cfg(target_os = "windows")]
+    #[cfg(any(target_os = "windows", target_os = "linux"))]
     {
-        before_open_handlers.push(caret_locator::get_handler());
+        before_open_handlers.push(gated(&features, |f| f.caret, caret_locator::get_handler()));
     };
 
     #[cfg(feature = "no-activate")]
@@ -176,7 +282,7 @@ fn get_handlers<'a>(ui: &EmojiPickerWindow) -> Handlers<'a> {
 
     #[cfg(feature = "key-shortcut")]
     #[cfg(target_os = "windows")]
-    {
+    if reopens {
         let key_shortcut = key_shortcut::KeyShortcut::create();
         if let Err(e) = key_shortcut {
             eprintln!("Failed to create a key shortcut. Reason: {}", e);
@@ -185,27 +291,51 @@ fn get_handlers<'a>(ui: &EmojiPickerWindow) -> Handlers<'a> {
         }
     };
 
+    #[cfg(feature = "key-shortcut")]
+    #[cfg(target_os = "linux")]
+    if reopens {
+        // Keys cannot be grabbed on Wayland, the portal asks the compositor instead.
+        let key_shortcut: Result<OpenerNotifier, String> =
+            if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+                portal_shortcut::PortalShortcut::create()
+                    .map(|s| Box::new(s) as OpenerNotifier)
+                    .map_err(|e| e.to_string())
+            } else {
+                x11_shortcut::X11Shortcut::create()
+                    .map(|s| Box::new(s) as OpenerNotifier)
+                    .map_err(|e| e.to_string())
+            };
+        match key_shortcut {
+            Ok(key_shortcut) => openers.push(key_shortcut),
+            Err(e) => eprintln!("Failed to create a key shortcut. Reason: {}", e),
+        }
+    };
+
     #[cfg(feature = "key-redir")]
     #[cfg(target_os = "windows")]
     {
-        on_open_handlers.push(key_redir::get_open_handler());
+        let open_handler = key_redir::get_open_handler();
+        on_open_handlers.push(gated(&features, |f| f.key_redir, open_handler));
         on_close_handlers.push(key_redir::get_close_handler());
     };
 
     #[cfg(feature = "tray-icon")]
     #[cfg(target_os = "windows")]
-    {
-        openers.push(tray_icon::initialize());
+    if reopens {
+        openers.push(tray_icon::initialize(features.clone()));
     };
 
     #[cfg(feature = "back-click")]
     #[cfg(target_os = "windows")]
-    if let Some(outside_click_handlers) = back_click::generate_handlers(ui) {
-        before_open_handlers.push(outside_click_handlers.on_open_handler);
-        on_close_handlers.push(outside_click_handlers.on_close_handler);
-        closers.push(outside_click_handlers.closer);
-    } else {
-        eprintln!("Failed to generate outside click handlers.");
+    {
+        if let Some(outside_click_handlers) = back_click::generate_handlers(ui) {
+            let open_handler = outside_click_handlers.on_open_handler;
+            before_open_handlers.push(gated(&features, |f| f.back_click, open_handler));
+            on_close_handlers.push(outside_click_handlers.on_close_handler);
+            closers.push(outside_click_handlers.closer);
+        } else {
+            eprintln!("Failed to generate outside click handlers.");
+        }
     }
 
     Handlers {
@@ -215,5 +345,7 @@ fn get_handlers<'a>(ui: &EmojiPickerWindow) -> Handlers<'a> {
         on_close_handlers,
         before_open_handlers,
         on_open_handlers,
+        poll_interval_ms: Arc::new(AtomicU64::new(settings.poll_interval_ms)),
+        features,
     }
 }
@@ -6,7 +6,7 @@ use windows::Win32::{
     },
 };
 
-use super::{utils::ToHWND, NotifierReason, OnOpenHandler};
+use super::{utils::ToHWND, OnOpenHandler};
 use crate::EmojiPickerWindow;
 use crate::{handler::Handler, SharedApp};
 
@@ -34,7 +34,7 @@ pub fn get_handler<'a>() -> OnOpenHandler<'a> {
     Handler::new(|args: &(SharedApp, _)| {
         let (app, reason) = args;
 
-        let enable_noactivate_if = *reason == NotifierReason::Shortcut;
+        let enable_noactivate_if = reason.is_shortcut();
 
         let _ = app
             .weak_ui()
@@ -7,7 +7,8 @@ use super::{CloserNotifier, NotifiersArgs};
 pub fn get_close_shortcut_notifier(ui: &EmojiPickerWindow) -> CloserNotifier {
     let (tx, rx) = std::sync::mpsc::sync_channel::<NotifiersArgs>(1);
     ui.on_close_requested(move || {
-        tx.send(super::NotifierReason::None).unwrap();
+        // The window may already be closing.
+        let _ = tx.try_send(super::NotifierReason::None);
     });
     Box::new(MpscNotifier::new(rx))
 }
@@ -0,0 +1,19 @@
+use crate::emoji_model;
+use crate::shortcut::OpenMode;
+use crate::{handler::Handler, SharedApp};
+
+use super::{NotifierReason, OnOpenHandler};
+
+/// Scrolls to the group of the shortcut that opened the picker, if any.
+pub fn get_handler<'a>() -> OnOpenHandler<'a> {
+    Handler::new(|args: &(SharedApp, NotifierReason)| {
+        let (app, reason) = args;
+        let NotifierReason::Shortcut(OpenMode::Group(group)) = reason else {
+            return;
+        };
+        let idx = emoji_model::group_index(*group) as i32;
+        let _ = app
+            .weak_ui()
+            .upgrade_in_event_loop(move |ui| ui.invoke_scroll_to_group(idx));
+    })
+}
@@ -0,0 +1,518 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, SyncSender};
+use std::time::Duration;
+
+use zbus::blocking::{Connection, Proxy};
+use zbus::zvariant::{OwnedObjectPath, OwnedValue, Value};
+
+use crate::handler::{MpscNotifier, Notifier};
+use crate::shortcut::{self, Accelerator, Binding, Key};
+
+use super::{NotifierReason, NotifiersArgs};
+
+const PORTAL_DESTINATION: &str = "org.freedesktop.portal.Desktop";
+const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
+const SHORTCUTS_INTERFACE: &str = "org.freedesktop.portal.GlobalShortcuts";
+const REQUEST_INTERFACE: &str = "org.freedesktop.portal.Request";
+const SESSION_INTERFACE: &str = "org.freedesktop.portal.Session";
+
+/// Interval between two checks of the bindings, to bind them again when they change.
+const BINDINGS_CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Makes the tokens of the requests unique.
+static TOKEN_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, thiserror::Error)]
+pub enum PortalShortcutError {
+    #[error("Failed to talk to the GlobalShortcuts portal. Reason: {0}")]
+    Dbus(#[from] zbus::Error),
+    #[error("The GlobalShortcuts portal refused to {0}.")]
+    Refused(&'static str),
+    #[error("The GlobalShortcuts portal did not return a session.")]
+    MissingSession,
+}
+
+/// Opens the picker when one of the shortcuts is pressed, through the
+/// `org.freedesktop.portal.GlobalShortcuts` portal. This is the only way
+/// to get global shortcuts on Wayland: the compositor asks the user to
+/// confirm (or change) them.
+pub struct PortalShortcut(MpscNotifier<NotifiersArgs>);
+
+impl PortalShortcut {
+    pub fn create() -> Result<Self, PortalShortcutError> {
+        Self::create_on(Connection::session()?)
+    }
+
+    /// Uses the portal of the given bus, e.g. a private bus with a mock of the portal.
+    /// The session is created in the background, as the portal may wait for the user
+    /// to confirm the shortcuts.
+    pub fn create_on(conn: Connection) -> Result<Self, PortalShortcutError> {
+        let portal = Proxy::new(&conn, PORTAL_DESTINATION, PORTAL_PATH, SHORTCUTS_INTERFACE)?;
+        let (tx, rx) = mpsc::sync_channel::<NotifiersArgs>(1);
+        std::thread::spawn(move || {
+            if let Err(e) = listen(&conn, &portal, tx) {
+                eprintln!("The portal shortcuts stopped working. {}", e);
+            }
+        });
+        Ok(Self(MpscNotifier::new(rx)))
+    }
+}
+
+impl Notifier<NotifiersArgs> for PortalShortcut {
+    fn has_notified(&self) -> Option<NotifiersArgs> {
+        self.0.has_notified()
+    }
+}
+
+/// Binds the shortcuts, sends their activations, and binds them again in a new
+/// session when they change (a session can only be bound once.)
+fn listen(
+    conn: &Connection,
+    portal: &Proxy<'static>,
+    tx: SyncSender<NotifiersArgs>,
+) -> Result<(), PortalShortcutError> {
+    // The signals are received in their own thread, so that the bindings can be checked.
+    let (activated_tx, activated_rx) = mpsc::channel::<(OwnedObjectPath, String)>();
+    let signals = portal.receive_signal("Activated")?;
+    std::thread::spawn(move || {
+        for signal in signals {
+            type Activated = (OwnedObjectPath, String, u64, HashMap<String, OwnedValue>);
+            if let Ok((session, id, _, _)) = signal.body::<Activated>() {
+                if activated_tx.send((session, id)).is_err() {
+                    break;
+                }
+            }
+        }
+    });
+
+    let mut bindings = shortcut::bindings();
+    let mut session = create_session(conn, portal)?;
+    bind(conn, portal, &session, &bindings)?;
+
+    loop {
+        match activated_rx.recv_timeout(BINDINGS_CHECK_INTERVAL) {
+            Ok((activated_session, id)) if activated_session == session => {
+                if let Some(binding) = binding_idx(&id).and_then(|idx| bindings.get(idx)) {
+                    // The picker may already be opening.
+                    let _ = tx.try_send(NotifierReason::Shortcut(binding.mode()));
+                }
+            }
+            Ok(_) | Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+
+        let current = shortcut::bindings();
+        if current != bindings {
+            close_session(conn, &session)?;
+            session = create_session(conn, portal)?;
+            bind(conn, portal, &session, &current)?;
+            bindings = current;
+        }
+    }
+}
+
+fn create_session(
+    conn: &Connection,
+    portal: &Proxy,
+) -> Result<OwnedObjectPath, PortalShortcutError> {
+    let token = new_token();
+    let options = HashMap::from([
+        ("handle_token", Value::from(token.as_str())),
+        ("session_handle_token", Value::from(new_token())),
+    ]);
+    let results = request(conn, portal, "CreateSession", &(options,), &token)
+        .map_err(|e| refused_as(e, "create a session"))?;
+
+    // Older portals return the handle as a string.
+    let handle = match results.get("session_handle").map(|v| &**v) {
+        Some(Value::ObjectPath(path)) => path.to_string(),
+        Some(Value::Str(path)) => path.to_string(),
+        _ => return Err(PortalShortcutError::MissingSession),
+    };
+    OwnedObjectPath::try_from(handle).map_err(|_| PortalShortcutError::MissingSession)
+}
+
+fn bind(
+    conn: &Connection,
+    portal: &Proxy,
+    session: &OwnedObjectPath,
+    bindings: &[Binding],
+) -> Result<(), PortalShortcutError> {
+    let shortcuts: Vec<(String, HashMap<&str, Value>)> = bindings
+        .iter()
+        .enumerate()
+        .map(|(idx, binding)| {
+            let description = format!("Open the emoji picker ({})", binding.keys);
+            let options = HashMap::from([
+                ("description", Value::from(description)),
+                ("preferred_trigger", Value::from(trigger(&binding.keys))),
+            ]);
+            (binding_id(idx), options)
+        })
+        .collect();
+    let token = new_token();
+    let options = HashMap::from([("handle_token", Value::from(token.as_str()))]);
+    request(
+        conn,
+        portal,
+        "BindShortcuts",
+        &(session, shortcuts, "", options),
+        &token,
+    )
+    .map_err(|e| refused_as(e, "bind the shortcuts"))?;
+    Ok(())
+}
+
+fn close_session(conn: &Connection, session: &OwnedObjectPath) -> Result<(), PortalShortcutError> {
+    let session = Proxy::new(
+        conn,
+        PORTAL_DESTINATION,
+        session.as_ref(),
+        SESSION_INTERFACE,
+    )?;
+    session.call_method("Close", &())?;
+    Ok(())
+}
+
+/// Calls a method of the portal and waits for the response of its request.
+/// The request is listened to before the call, so that the response is not missed.
+fn request<B>(
+    conn: &Connection,
+    portal: &Proxy,
+    method: &'static str,
+    body: &B,
+    token: &str,
+) -> Result<HashMap<String, OwnedValue>, PortalShortcutError>
+where
+    B: serde::Serialize + zbus::zvariant::DynamicType,
+{
+    let sender = conn
+        .unique_name()
+        .map(|name| name.trim_start_matches(':').replace('.', "_"))
+        .unwrap_or_default();
+    let path = format!("{PORTAL_PATH}/request/{sender}/{token}");
+    let request = Proxy::new(conn, PORTAL_DESTINATION, path, REQUEST_INTERFACE)?;
+    let mut responses = request.receive_signal("Response")?;
+
+    portal.call_method(method, body)?;
+
+    let response = responses
+        .next()
+        .ok_or(PortalShortcutError::Refused(method))?;
+    let (code, results): (u32, HashMap<String, OwnedValue>) = response.body()?;
+    if code != 0 {
+        return Err(PortalShortcutError::Refused(method));
+    }
+    Ok(results)
+}
+
+/// Describes a refusal of the portal with what was being done.
+fn refused_as(e: PortalShortcutError, action: &'static str) -> PortalShortcutError {
+    match e {
+        PortalShortcutError::Refused(_) => PortalShortcutError::Refused(action),
+        e => e,
+    }
+}
+
+/// Tokens are used in object paths, so they are made of letters, digits and underscores.
+fn new_token() -> String {
+    let count = TOKEN_COUNTER.fetch_add(1, Ordering::Relaxed);
+    format!("emoji_picker_{}_{}", std::process::id(), count)
+}
+
+fn binding_id(idx: usize) -> String {
+    format!("open-{}", idx)
+}
+
+fn binding_idx(id: &str) -> Option<usize> {
+    id.strip_prefix("open-")?.parse().ok()
+}
+
+/// Converts an accelerator to a trigger of the XDG shortcuts specification,
+/// e.g. "CTRL+ALT+e" or "LOGO+period".
+fn trigger(accelerator: &Accelerator) -> String {
+    let modifiers = accelerator.modifiers;
+    let mut trigger = String::new();
+    for (held, name) in [
+        (modifiers.ctrl, "CTRL"),
+        (modifiers.alt, "ALT"),
+        (modifiers.shift, "SHIFT"),
+        (modifiers.logo, "LOGO"),
+    ] {
+        if held {
+            trigger.push_str(name);
+            trigger.push('+');
+        }
+    }
+    trigger.push_str(&keysym_name(accelerator.key));
+    trigger
+}
+
+/// Returns the XKB name of the keysym of a key.
+fn keysym_name(key: Key) -> String {
+    let name = match key {
+        Key::Char(c) if c.is_ascii_alphanumeric() => return c.to_string(),
+        Key::Char('.') => "period",
+        Key::Char(',') => "comma",
+        Key::Char('-') => "minus",
+        Key::Char('=') => "equal",
+        Key::Char(';') => "semicolon",
+        Key::Char('/') => "slash",
+        Key::Char('\\') => "backslash",
+        Key::Char('\'') => "apostrophe",
+        Key::Char('`') => "grave",
+        Key::Char('[') => "bracketleft",
+        Key::Char(']') => "bracketright",
+        Key::Char(c) => return c.to_string(),
+        Key::F(n) => return format!("F{}", n),
+        Key::Space => "space",
+        Key::Enter => "Return",
+        Key::Tab => "Tab",
+        Key::Escape => "Escape",
+        Key::Backspace => "BackSpace",
+        Key::Insert => "Insert",
+        Key::Delete => "Delete",
+        Key::Home => "Home",
+        Key::End => "End",
+        Key::PageUp => "Page_Up",
+        Key::PageDown => "Page_Down",
+        Key::Up => "Up",
+        Key::Down => "Down",
+        Key::Left => "Left",
+        Key::Right => "Right",
+    };
+    name.to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader};
+    use std::process::{Child, Command, Stdio};
+    use std::sync::{Arc, Mutex};
+    use std::time::Instant;
+
+    use zbus::blocking::ConnectionBuilder;
+    use zbus::MessageHeader;
+
+    use super::*;
+    use crate::shortcut::{OpenGroup, OpenMode};
+
+    /// A private bus, stopped when dropped.
+    struct Bus {
+        daemon: Child,
+        address: String,
+    }
+
+    impl Drop for Bus {
+        fn drop(&mut self) {
+            let _ = self.daemon.kill();
+            let _ = self.daemon.wait();
+        }
+    }
+
+    /// Starts a bus. Returns None if dbus-daemon is not installed.
+    fn start_bus() -> Option<Bus> {
+        let mut daemon = Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--print-address"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .ok()?;
+        let mut address = String::new();
+        BufReader::new(daemon.stdout.take().unwrap())
+            .read_line(&mut address)
+            .unwrap();
+        let address = address.trim().to_owned();
+        Some(Bus { daemon, address })
+    }
+
+    #[derive(Default)]
+    struct MockState {
+        /// The sessions that were bound, with the ids and triggers of their shortcuts.
+        bound: Vec<(OwnedObjectPath, Vec<(String, String)>)>,
+        closed: Vec<OwnedObjectPath>,
+    }
+
+    /// A GlobalShortcuts portal that accepts everything.
+    struct MockPortal(Arc<Mutex<MockState>>);
+
+    struct MockSession {
+        state: Arc<Mutex<MockState>>,
+        path: OwnedObjectPath,
+    }
+
+    fn string_option(options: &HashMap<String, OwnedValue>, key: &str) -> String {
+        match options.get(key).map(|v| &**v) {
+            Some(Value::Str(s)) => s.to_string(),
+            _ => panic!("Missing the {key} option"),
+        }
+    }
+
+    fn handle(header: &MessageHeader<'_>, kind: &str, token: &str) -> OwnedObjectPath {
+        let sender = header.sender().unwrap().unwrap();
+        let sender = sender.trim_start_matches(':').replace('.', "_");
+        OwnedObjectPath::try_from(format!("{PORTAL_PATH}/{kind}/{sender}/{token}")).unwrap()
+    }
+
+    async fn respond(
+        conn: &zbus::Connection,
+        request: &OwnedObjectPath,
+        results: HashMap<&str, Value<'_>>,
+    ) -> zbus::fdo::Result<()> {
+        conn.emit_signal(
+            None::<&str>,
+            request.as_ref(),
+            REQUEST_INTERFACE,
+            "Response",
+            &(0u32, results),
+        )
+        .await?;
+        Ok(())
+    }
+
+    #[zbus::dbus_interface(name = "org.freedesktop.portal.GlobalShortcuts")]
+    impl MockPortal {
+        async fn create_session(
+            &self,
+            #[zbus(header)] header: MessageHeader<'_>,
+            #[zbus(connection)] conn: &zbus::Connection,
+            #[zbus(object_server)] server: &zbus::ObjectServer,
+            options: HashMap<String, OwnedValue>,
+        ) -> zbus::fdo::Result<OwnedObjectPath> {
+            let request = handle(&header, "request", &string_option(&options, "handle_token"));
+            let token = string_option(&options, "session_handle_token");
+            let session = handle(&header, "session", &token);
+            let state = self.0.clone();
+            let path = session.clone();
+            server
+                .at(session.as_ref(), MockSession { state, path })
+                .await?;
+            let results = HashMap::from([("session_handle", Value::from(session.as_ref()))]);
+            respond(conn, &request, results).await?;
+            Ok(request)
+        }
+
+        async fn bind_shortcuts(
+            &self,
+            #[zbus(header)] header: MessageHeader<'_>,
+            #[zbus(connection)] conn: &zbus::Connection,
+            session: OwnedObjectPath,
+            shortcuts: Vec<(String, HashMap<String, OwnedValue>)>,
+            _parent_window: String,
+            options: HashMap<String, OwnedValue>,
+        ) -> zbus::fdo::Result<OwnedObjectPath> {
+            let request = handle(&header, "request", &string_option(&options, "handle_token"));
+            let shortcuts = shortcuts
+                .iter()
+                .map(|(id, options)| (id.clone(), string_option(options, "preferred_trigger")))
+                .collect();
+            self.0.lock().unwrap().bound.push((session, shortcuts));
+            respond(conn, &request, HashMap::new()).await?;
+            Ok(request)
+        }
+    }
+
+    #[zbus::dbus_interface(name = "org.freedesktop.portal.Session")]
+    impl MockSession {
+        fn close(&self) {
+            self.state.lock().unwrap().closed.push(self.path.clone());
+        }
+    }
+
+    /// Waits a bit for the condition on the state of the portal.
+    fn wait_for(state: &Mutex<MockState>, condition: impl Fn(&MockState) -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !condition(&state.lock().unwrap()) {
+            assert!(Instant::now() < deadline, "The portal was not called");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    /// Waits a bit for the shortcut to notify.
+    fn notified(shortcut: &PortalShortcut) -> Option<NotifiersArgs> {
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while Instant::now() < deadline {
+            if let Some(reason) = shortcut.has_notified() {
+                return Some(reason);
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        None
+    }
+
+    fn activate(portal: &Connection, session: &OwnedObjectPath, id: &str) {
+        let details: HashMap<&str, Value> = HashMap::new();
+        portal
+            .emit_signal(
+                None::<&str>,
+                PORTAL_PATH,
+                SHORTCUTS_INTERFACE,
+                "Activated",
+                &(session, id, 0u64, details),
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn binds_and_activates_the_shortcuts() {
+        let Some(bus) = start_bus() else {
+            eprintln!("dbus-daemon is not installed, skipping the test.");
+            return;
+        };
+        let state = Arc::new(Mutex::new(MockState::default()));
+        let portal = ConnectionBuilder::address(bus.address.as_str())
+            .unwrap()
+            .name(PORTAL_DESTINATION)
+            .unwrap()
+            .serve_at(PORTAL_PATH, MockPortal(state.clone()))
+            .unwrap()
+            .build()
+            .unwrap();
+        let conn = ConnectionBuilder::address(bus.address.as_str())
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let _lock = shortcut::TEST_BINDINGS_LOCK.lock().unwrap();
+        shortcut::set_bindings(vec![Binding {
+            keys: "Ctrl+E".parse().unwrap(),
+            group: Some(OpenGroup::Favorites),
+        }]);
+        let shortcut = PortalShortcut::create_on(conn).unwrap();
+        wait_for(&state, |state| !state.bound.is_empty());
+        let (first, shortcuts) = state.lock().unwrap().bound[0].clone();
+        assert_eq!(shortcuts, [("open-0".to_owned(), "CTRL+e".to_owned())]);
+
+        activate(&portal, &first, "open-0");
+        assert_eq!(
+            notified(&shortcut),
+            Some(NotifierReason::Shortcut(OpenMode::Group(
+                OpenGroup::Favorites
+            )))
+        );
+        activate(&portal, &first, "open-1");
+        assert_eq!(notified(&shortcut), None);
+
+        // A new session is bound when the bindings change.
+        shortcut::set_bindings(vec![Binding {
+            keys: "Ctrl+Shift+F".parse().unwrap(),
+            group: None,
+        }]);
+        wait_for(&state, |state| state.bound.len() == 2);
+        let (second, shortcuts) = state.lock().unwrap().bound[1].clone();
+        assert_eq!(
+            shortcuts,
+            [("open-0".to_owned(), "CTRL+SHIFT+f".to_owned())]
+        );
+        assert_eq!(state.lock().unwrap().closed, std::slice::from_ref(&first));
+
+        activate(&portal, &first, "open-0");
+        assert_eq!(notified(&shortcut), None);
+        activate(&portal, &second, "open-0");
+        assert_eq!(
+            notified(&shortcut),
+            Some(NotifierReason::Shortcut(OpenMode::Default))
+        );
+    }
+}
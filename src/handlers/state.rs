@@ -0,0 +1,28 @@
+use slint::ComponentHandle as _;
+
+use crate::state::State;
+use crate::{handler::Handler, SharedApp};
+
+use super::OnCloseHandler;
+
+/// Saves the skin tone, the size and position of the window and the displayed group
+/// when the window closes, so that they are restored on the next run.
+pub fn get_close_handler<'a>() -> OnCloseHandler<'a> {
+    Handler::new(|args: &(SharedApp, _)| {
+        let (app, _) = args;
+        let _ = app.weak_ui().upgrade_in_event_loop(|ui| {
+            let window = ui.window();
+            let size = window.size().to_logical(window.scale_factor());
+            let position = window.position();
+            let state = State {
+                skin_tone: ui.get_selected_skin_tone(),
+                window_size: Some((size.width, size.height)),
+                window_position: Some((position.x, position.y)),
+                group: ui.invoke_current_group(),
+            };
+            if let Err(e) = state.save() {
+                eprintln!("Failed to save the state of the picker. Reason: {}", e);
+            }
+        });
+    })
+}
@@ -1,20 +1,35 @@
-use std::sync::mpsc;
+use std::sync::{mpsc, Arc, Mutex, RwLock};
 use tray_item::TrayItem;
 
 use crate::handler::{MpscNotifier, Notifier};
+use crate::settings::Features;
 
 use super::{NotifiersArgs, OpenerNotifier};
 
 struct TrayIconNotifier {
     np: MpscNotifier<NotifiersArgs>,
+    tx: mpsc::SyncSender<NotifiersArgs>,
+    features: Arc<RwLock<Features>>,
     // If not there, the tray icon will be destroyed.
-    #[allow(dead_code)]
-    t: TrayItem, // To prevent the tray icon from being dropped.
+    // It is created and destroyed as the settings turn it on and off.
+    t: Mutex<Option<TrayItem>>,
 }
 
 /// This returns the notifier that opens.
-pub fn initialize() -> OpenerNotifier {
+/// The tray icon is shown while the settings turn it on.
+pub fn initialize(features: Arc<RwLock<Features>>) -> OpenerNotifier {
     let (tx, rx) = mpsc::sync_channel::<NotifiersArgs>(1);
+    let notifier = TrayIconNotifier {
+        np: MpscNotifier::new(rx),
+        tx,
+        features,
+        t: Mutex::new(None),
+    };
+    notifier.update_icon();
+    Box::new(notifier)
+}
+
+fn create_tray_item(tx: mpsc::SyncSender<NotifiersArgs>) -> TrayItem {
     let mut t =
         TrayItem::new("Emoji picker", tray_item::IconSource::Resource("tray-icon")).unwrap();
 
@@ -29,14 +44,23 @@ pub fn initialize() -> OpenerNotifier {
     })
     .unwrap();
 
-    Box::new(TrayIconNotifier {
-        np: MpscNotifier::new(rx),
-        t,
-    })
+    t
+}
+
+impl TrayIconNotifier {
+    /// Creates or destroys the tray icon if the settings changed.
+    fn update_icon(&self) {
+        let enabled = self.features.read().unwrap().tray_icon;
+        let mut t = self.t.lock().unwrap();
+        if enabled != t.is_some() {
+            *t = enabled.then(|| create_tray_item(self.tx.clone()));
+        }
+    }
 }
 
 impl Notifier<NotifiersArgs> for TrayIconNotifier {
     fn has_notified(&self) -> Option<NotifiersArgs> {
+        self.update_icon();
         self.np.has_notified()
     }
 }
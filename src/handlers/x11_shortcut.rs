@@ -0,0 +1,347 @@
+use std::sync::mpsc::{self, SyncSender};
+use std::sync::{Arc, Mutex};
+
+use x11rb::connection::Connection;
+use x11rb::errors::{ConnectError, ConnectionError, ReplyError};
+use x11rb::protocol::xproto::{ConnectionExt as _, GrabMode, ModMask, Window};
+use x11rb::protocol::Event;
+use x11rb::rust_connection::RustConnection;
+
+use crate::handler::{MpscNotifier, Notifier};
+use crate::shortcut::{self, Binding, Key, Modifiers};
+
+use super::{NotifierReason, NotifiersArgs};
+
+#[derive(Debug, thiserror::Error)]
+pub enum X11ShortcutError {
+    #[error("Failed to connect to the X server. Reason: {0}")]
+    Connect(#[from] ConnectError),
+    #[error("Lost the connection to the X server. Reason: {0}")]
+    Connection(#[from] ConnectionError),
+    #[error("The X server refused a request. Reason: {0}")]
+    Reply(#[from] ReplyError),
+}
+
+/// Opens the picker when one of the shortcuts is pressed, by grabbing
+/// the keys on the root window of the X server.
+/// The keys are grabbed again when the bindings change, as the notifier is checked.
+pub struct X11Shortcut {
+    notifier: MpscNotifier<NotifiersArgs>,
+    conn: Arc<RustConnection>,
+    root: Window,
+    grabs: Arc<Mutex<Grabs>>,
+}
+
+impl X11Shortcut {
+    pub fn create() -> Result<Self, X11ShortcutError> {
+        Self::create_on(None)
+    }
+
+    /// Grabs the keys on the given X display, or on `$DISPLAY`.
+    pub fn create_on(display: Option<&str>) -> Result<Self, X11ShortcutError> {
+        let (conn, screen) = x11rb::connect(display)?;
+        let conn = Arc::new(conn);
+        let root = conn.setup().roots[screen].root;
+        let bindings = shortcut::bindings();
+        let grabs = Arc::new(Mutex::new(Grabs {
+            grabs: grab(&conn, root, &bindings)?,
+            bindings,
+        }));
+        let (tx, rx) = mpsc::sync_channel::<NotifiersArgs>(1);
+
+        std::thread::spawn({
+            let (conn, grabs) = (conn.clone(), grabs.clone());
+            move || {
+                if let Err(e) = listen(&conn, &grabs, tx) {
+                    eprintln!("The X11 shortcuts stopped working. {}", e);
+                }
+            }
+        });
+        Ok(Self {
+            notifier: MpscNotifier::new(rx),
+            conn,
+            root,
+            grabs,
+        })
+    }
+
+    /// Grabs the bindings again if they changed.
+    fn update_grabs(&self) -> Result<(), X11ShortcutError> {
+        let current = shortcut::bindings();
+        let mut grabs = self.grabs.lock().unwrap();
+        if current != grabs.bindings {
+            ungrab(&self.conn, self.root, &grabs.grabs)?;
+            grabs.grabs = grab(&self.conn, self.root, &current)?;
+            grabs.bindings = current;
+        }
+        Ok(())
+    }
+}
+
+impl Notifier<NotifiersArgs> for X11Shortcut {
+    fn has_notified(&self) -> Option<NotifiersArgs> {
+        if let Err(e) = self.update_grabs() {
+            eprintln!("Failed to grab the new shortcuts. {}", e);
+        }
+        self.notifier.has_notified()
+    }
+}
+
+/// The bindings, and the key combinations grabbed for them.
+struct Grabs {
+    bindings: Vec<Binding>,
+    grabs: Vec<Grab>,
+}
+
+/// A key combination grabbed on the X server.
+struct Grab {
+    keycode: u8,
+    key: Key,
+    modifiers: Modifiers,
+}
+
+/// Sends the presses of the grabbed keys, waiting for them.
+fn listen(
+    conn: &RustConnection,
+    grabs: &Mutex<Grabs>,
+    tx: SyncSender<NotifiersArgs>,
+) -> Result<(), X11ShortcutError> {
+    loop {
+        let Event::KeyPress(event) = conn.wait_for_event()? else {
+            continue;
+        };
+        let modifiers = modifiers_from_state(event.state.into());
+        let mode = {
+            let grabs = grabs.lock().unwrap();
+            grabs
+                .grabs
+                .iter()
+                .find(|g| g.keycode == event.detail)
+                .and_then(|g| shortcut::find(&grabs.bindings, modifiers, g.key))
+        };
+        if let Some(mode) = mode {
+            // The picker may already be opening.
+            let _ = tx.try_send(NotifierReason::Shortcut(mode));
+        }
+    }
+}
+
+fn grab(
+    conn: &RustConnection,
+    root: Window,
+    bindings: &[Binding],
+) -> Result<Vec<Grab>, X11ShortcutError> {
+    let setup = conn.setup();
+    let (min, max) = (setup.min_keycode, setup.max_keycode);
+    let mapping = conn.get_keyboard_mapping(min, max - min + 1)?.reply()?;
+    let per_keycode = usize::from(mapping.keysyms_per_keycode).max(1);
+
+    let mut grabs = vec![];
+    for binding in bindings {
+        let keysym = keysym(binding.keys.key);
+        let Some(keycode) = mapping
+            .keysyms
+            .chunks(per_keycode)
+            .position(|keysyms| keysyms.contains(&keysym))
+            .map(|idx| min + idx as u8)
+        else {
+            eprintln!("No key of the keyboard produces {}.", binding.keys);
+            continue;
+        };
+
+        let modifiers = mod_mask(binding.keys.modifiers);
+        let mut grabbed = true;
+        for ignored in ignored_masks() {
+            let cookie = conn.grab_key(
+                false,
+                root,
+                modifiers | ignored,
+                keycode,
+                GrabMode::ASYNC,
+                GrabMode::ASYNC,
+            )?;
+            grabbed &= cookie.check().is_ok();
+        }
+        if !grabbed {
+            eprintln!("{} is already used by another application.", binding.keys);
+        }
+        grabs.push(Grab {
+            keycode,
+            key: binding.keys.key,
+            modifiers: binding.keys.modifiers,
+        });
+    }
+    conn.flush()?;
+    Ok(grabs)
+}
+
+fn ungrab(conn: &RustConnection, root: Window, grabs: &[Grab]) -> Result<(), X11ShortcutError> {
+    for grab in grabs {
+        for ignored in ignored_masks() {
+            conn.ungrab_key(grab.keycode, root, mod_mask(grab.modifiers) | ignored)?;
+        }
+    }
+    conn.flush()?;
+    Ok(())
+}
+
+/// Caps Lock and Num Lock must not prevent the shortcuts from working,
+/// so every binding is grabbed with and without them.
+fn ignored_masks() -> [ModMask; 4] {
+    [
+        ModMask::from(0u16),
+        ModMask::LOCK,
+        ModMask::M2,
+        ModMask::LOCK | ModMask::M2,
+    ]
+}
+
+fn mod_mask(modifiers: Modifiers) -> ModMask {
+    let mut mask = ModMask::from(0u16);
+    for (held, bit) in [
+        (modifiers.shift, ModMask::SHIFT),
+        (modifiers.ctrl, ModMask::CONTROL),
+        (modifiers.alt, ModMask::M1),
+        (modifiers.logo, ModMask::M4),
+    ] {
+        if held {
+            mask |= bit;
+        }
+    }
+    mask
+}
+
+/// Converts the state of a key event to the held modifiers, ignoring the locks.
+fn modifiers_from_state(state: u16) -> Modifiers {
+    let held = |mask: ModMask| state & u16::from(mask) != 0;
+    Modifiers {
+        ctrl: held(ModMask::CONTROL),
+        alt: held(ModMask::M1),
+        shift: held(ModMask::SHIFT),
+        logo: held(ModMask::M4),
+    }
+}
+
+/// Returns the X keysym of a key (see `X11/keysymdef.h`.)
+fn keysym(key: Key) -> u32 {
+    match key {
+        // Latin-1 keysyms are the same as the characters.
+        Key::Char(c) => u32::from(c),
+        Key::F(n) => 0xffbe + u32::from(n) - 1,
+        Key::Space => 0x0020,
+        Key::Enter => 0xff0d,
+        Key::Tab => 0xff09,
+        Key::Escape => 0xff1b,
+        Key::Backspace => 0xff08,
+        Key::Insert => 0xff63,
+        Key::Delete => 0xffff,
+        Key::Home => 0xff50,
+        Key::End => 0xff57,
+        Key::PageUp => 0xff55,
+        Key::PageDown => 0xff56,
+        Key::Up => 0xff52,
+        Key::Down => 0xff54,
+        Key::Left => 0xff51,
+        Key::Right => 0xff53,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use x11rb::protocol::xproto::{KEY_PRESS_EVENT, KEY_RELEASE_EVENT};
+    use x11rb::protocol::xtest::ConnectionExt as _;
+    use x11rb::CURRENT_TIME;
+
+    use super::*;
+    use crate::shortcut::{OpenGroup, OpenMode};
+    use crate::xvfb::Xvfb;
+
+    /// Presses and releases the key producing the keysym, with Control held if asked.
+    fn press(conn: &RustConnection, keysym: u32, ctrl: bool) {
+        let setup = conn.setup();
+        let (min, max) = (setup.min_keycode, setup.max_keycode);
+        let mapping = conn
+            .get_keyboard_mapping(min, max - min + 1)
+            .unwrap()
+            .reply()
+            .unwrap();
+        let per_keycode = usize::from(mapping.keysyms_per_keycode);
+        let keycode = |keysym: u32| {
+            let idx = mapping
+                .keysyms
+                .chunks(per_keycode)
+                .position(|keysyms| keysyms.contains(&keysym))
+                .unwrap();
+            min + idx as u8
+        };
+        let root = setup.roots[0].root;
+        let fake = |kind: u8, keycode: u8| {
+            conn.xtest_fake_input(kind, keycode, CURRENT_TIME, root, 0, 0, 0)
+                .unwrap();
+        };
+        const CONTROL_L: u32 = 0xffe3;
+        if ctrl {
+            fake(KEY_PRESS_EVENT, keycode(CONTROL_L));
+        }
+        fake(KEY_PRESS_EVENT, keycode(keysym));
+        fake(KEY_RELEASE_EVENT, keycode(keysym));
+        if ctrl {
+            fake(KEY_RELEASE_EVENT, keycode(CONTROL_L));
+        }
+        // A round trip makes sure the server handled the fake input.
+        conn.get_input_focus().unwrap().reply().unwrap();
+    }
+
+    /// Waits a bit for the shortcut to notify.
+    fn notified(shortcut: &X11Shortcut) -> Option<NotifiersArgs> {
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while Instant::now() < deadline {
+            if let Some(reason) = shortcut.has_notified() {
+                return Some(reason);
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        None
+    }
+
+    #[test]
+    #[ignore = "needs Xvfb"]
+    fn opens_on_the_grabbed_keys() {
+        let xvfb = Xvfb::start();
+        let display = &xvfb.display;
+        let _lock = shortcut::TEST_BINDINGS_LOCK.lock().unwrap();
+        shortcut::set_bindings(vec![Binding {
+            keys: "Ctrl+E".parse().unwrap(),
+            group: Some(OpenGroup::Favorites),
+        }]);
+        let shortcut = X11Shortcut::create_on(Some(display)).unwrap();
+        let (conn, _) = x11rb::connect(Some(display)).unwrap();
+
+        press(&conn, u32::from('e'), true);
+        assert_eq!(
+            notified(&shortcut),
+            Some(NotifierReason::Shortcut(OpenMode::Group(
+                OpenGroup::Favorites
+            )))
+        );
+        // The modifiers must be exactly the ones of the binding.
+        press(&conn, u32::from('e'), false);
+        assert_eq!(notified(&shortcut), None);
+
+        // The keys are grabbed again when the bindings change.
+        shortcut::set_bindings(vec![Binding {
+            keys: "Ctrl+F".parse().unwrap(),
+            group: None,
+        }]);
+        assert_eq!(notified(&shortcut), None);
+        press(&conn, u32::from('e'), true);
+        assert_eq!(notified(&shortcut), None);
+        press(&conn, u32::from('f'), true);
+        assert_eq!(
+            notified(&shortcut),
+            Some(NotifierReason::Shortcut(OpenMode::Default))
+        );
+    }
+}
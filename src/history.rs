@@ -0,0 +1,302 @@
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::emoji::EmojiWrapper;
+
+/// How many emojis are kept in the "Recently used" group.
+const RECENT_CAPACITY: usize = 32;
+
+/// How many emojis are remembered to compute their frecency.
+const HISTORY_CAPACITY: usize = 512;
+
+/// Time (in seconds) after which the usage count of an emoji is halved.
+const HALF_LIFE: f64 = 7.0 * 24.0 * 60.0 * 60.0;
+
+/// Name of the file, in the user's data directory, where the history is stored.
+const HISTORY_FILE: &str = "recent.txt";
+
+/// How an emoji has been used.
+struct Usage {
+    code: String,
+    /// Usage count, decayed up to `last_used`.
+    count: f64,
+    /// UNIX timestamp (in seconds) of the last use.
+    last_used: u64,
+}
+
+impl Usage {
+    /// Returns the usage count decayed up to `now`.
+    fn frecency(&self, now: u64) -> f64 {
+        let elapsed = now.saturating_sub(self.last_used) as f64;
+        self.count * 0.5f64.powf(elapsed / HALF_LIFE)
+    }
+}
+
+/// The emojis that were selected, the most recent first.
+/// Codes are stored as-is, so the selected skin tone variant is remembered.
+///
+/// The file contains one emoji per line, with its usage count and the
+/// timestamp of its last use, separated by tabs.
+pub struct EmojiHistory {
+    usages: Vec<Usage>,
+    path: Option<PathBuf>,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+impl EmojiHistory {
+    /// Loads the history from the user's data directory.
+    /// A missing or unreadable file results in an empty history.
+    pub fn load() -> Self {
+        let path = crate::paths::data_file(HISTORY_FILE);
+        let usages = path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|content| {
+                content
+                    .lines()
+                    .filter_map(EmojiHistory::parse_line)
+                    .take(HISTORY_CAPACITY)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Self { usages, path }
+    }
+
+    fn parse_line(line: &str) -> Option<Usage> {
+        let mut fields = line.trim().split('\t');
+        let code = fields.next().filter(|code| emojis::get(code).is_some())?;
+        let count = fields.next().and_then(|c| c.parse().ok()).unwrap_or(1.0);
+        let last_used = fields
+            .next()
+            .and_then(|t| t.parse().ok())
+            .unwrap_or_default();
+        Some(Usage {
+            code: code.to_owned(),
+            count,
+            last_used,
+        })
+    }
+
+    /// Records a use of the given code, moving it at the front of the history.
+    pub fn record(&mut self, code: &str) {
+        if emojis::get(code).is_none() {
+            return;
+        }
+        let now = now();
+        let count = match self.usages.iter().position(|u| u.code == code) {
+            Some(idx) => self.usages.remove(idx).frecency(now),
+            None => 0.0,
+        };
+        self.usages.insert(
+            0,
+            Usage {
+                code: code.to_owned(),
+                count: count + 1.0,
+                last_used: now,
+            },
+        );
+        self.usages.truncate(HISTORY_CAPACITY);
+    }
+
+    /// Writes the history to the user's data directory.
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let content: String = self
+            .usages
+            .iter()
+            .map(|u| format!("{}\t{}\t{}\n", u.code, u.count, u.last_used))
+            .collect();
+        fs::write(path, content)
+    }
+
+    /// Returns the recently used emojis, the most recent first.
+    pub fn emojis(&self) -> impl Iterator<Item = EmojiWrapper> + '_ {
+        self.usages
+            .iter()
+            .take(RECENT_CAPACITY)
+            .filter_map(|u| emojis::get(&u.code))
+            .map(EmojiWrapper)
+    }
+
+    /// Returns the frecency (decayed usage count) of every used emoji.
+    /// The skin tone variants of an emoji are accounted to the emoji itself.
+    pub fn frecencies(&self) -> HashMap<EmojiWrapper, f64> {
+        let now = now();
+        let mut frecencies = HashMap::new();
+        for usage in self.usages.iter() {
+            if let Some(emoji) = emojis::get(&usage.code) {
+                *frecencies.entry(EmojiWrapper(emoji).base()).or_default() += usage.frecency(now);
+            }
+        }
+        frecencies
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(usages: &[(&str, f64, u64)]) -> EmojiHistory {
+        let usages = usages
+            .iter()
+            .map(|&(code, count, last_used)| Usage {
+                code: code.to_owned(),
+                count,
+                last_used,
+            })
+            .collect();
+        EmojiHistory { usages, path: None }
+    }
+
+    fn emoji(code: &str) -> EmojiWrapper {
+        EmojiWrapper(emojis::get(code).unwrap())
+    }
+
+    fn codes(history: &EmojiHistory) -> Vec<&str> {
+        history.usages.iter().map(|u| u.code.as_str()).collect()
+    }
+
+    #[test]
+    fn reads_a_line_with_its_count_and_timestamp() {
+        let usage = EmojiHistory::parse_line("👍\t2.5\t1700000000\n").unwrap();
+        assert_eq!(usage.code, "👍");
+        assert_eq!(usage.count, 2.5);
+        assert_eq!(usage.last_used, 1700000000);
+    }
+
+    #[test]
+    fn defaults_the_missing_or_malformed_fields() {
+        let usage = EmojiHistory::parse_line("👍").unwrap();
+        assert_eq!((usage.count, usage.last_used), (1.0, 0));
+        let usage = EmojiHistory::parse_line("👍\tmany\tyesterday").unwrap();
+        assert_eq!((usage.count, usage.last_used), (1.0, 0));
+    }
+
+    #[test]
+    fn skips_the_lines_without_an_emoji() {
+        assert!(EmojiHistory::parse_line("").is_none());
+        assert!(EmojiHistory::parse_line("thumbsup\t3\t1700000000").is_none());
+        assert!(EmojiHistory::parse_line("\t3\t1700000000").is_none());
+    }
+
+    #[test]
+    fn records_a_new_emoji_first() {
+        let mut history = history(&[("👍", 1.0, now())]);
+        history.record("🎉");
+        assert_eq!(codes(&history), ["🎉", "👍"]);
+        assert_eq!(history.usages[0].count, 1.0);
+    }
+
+    #[test]
+    fn records_an_existing_emoji_once_and_moves_it_first() {
+        let mut history = history(&[("👍", 1.0, now()), ("🎉", 3.0, now())]);
+        history.record("🎉");
+        assert_eq!(codes(&history), ["🎉", "👍"]);
+        assert!((history.usages[0].count - 4.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn ignores_a_code_that_is_not_an_emoji() {
+        let mut history = history(&[("👍", 1.0, now())]);
+        history.record("thumbsup");
+        assert_eq!(codes(&history), ["👍"]);
+    }
+
+    #[test]
+    fn keeps_the_skin_tone_of_the_recent_emojis() {
+        let mut history = history(&[]);
+        history.record("👍🏽");
+        assert_eq!(
+            history.emojis().map(|e| e.0.as_str()).collect::<Vec<_>>(),
+            ["👍🏽"]
+        );
+    }
+
+    #[test]
+    fn halves_the_count_every_half_life() {
+        let usage = Usage {
+            code: "👍".to_owned(),
+            count: 8.0,
+            last_used: 1000,
+        };
+        let half_life = HALF_LIFE as u64;
+        assert_eq!(usage.frecency(1000), 8.0);
+        assert!((usage.frecency(1000 + half_life) - 4.0).abs() < 1e-9);
+        assert!((usage.frecency(1000 + 3 * half_life) - 1.0).abs() < 1e-9);
+        // A clock set back does not increase the count.
+        assert_eq!(usage.frecency(0), 8.0);
+    }
+
+    #[test]
+    fn ranks_a_recent_emoji_above_an_older_one_used_as_often() {
+        let now = now();
+        let history = history(&[("👍", 4.0, now), ("🎉", 4.0, now - 2 * HALF_LIFE as u64)]);
+        let frecencies = history.frecencies();
+        assert!(frecencies[&emoji("👍")] > frecencies[&emoji("🎉")]);
+    }
+
+    #[test]
+    fn ranks_a_frequent_emoji_above_a_rarer_one_used_as_recently() {
+        let now = now();
+        let history = history(&[("👍", 1.0, now), ("🎉", 5.0, now)]);
+        let frecencies = history.frecencies();
+        assert!(frecencies[&emoji("🎉")] > frecencies[&emoji("👍")]);
+    }
+
+    #[test]
+    fn ranks_an_old_favorite_below_a_recent_habit() {
+        let now = now();
+        let history = history(&[("👍", 3.0, now), ("🎉", 10.0, now - 4 * HALF_LIFE as u64)]);
+        let frecencies = history.frecencies();
+        assert!(frecencies[&emoji("👍")] > frecencies[&emoji("🎉")]);
+    }
+
+    #[test]
+    fn accounts_the_skin_tones_to_the_emoji() {
+        let now = now();
+        let history = history(&[("👍🏽", 2.0, now), ("👍", 1.0, now)]);
+        let frecencies = history.frecencies();
+        assert_eq!(frecencies.len(), 1);
+        assert!((frecencies[&emoji("👍")] - 3.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn saves_the_lines_it_reads() {
+        let path =
+            std::env::temp_dir().join(format!("emoji-picker-history-{}", std::process::id()));
+        let mut saved = history(&[("👍🏽", 2.5, 1700000000), ("🎉", 1.0, 1600000000)]);
+        saved.path = Some(path.clone());
+        saved.save().unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let read: Vec<_> = content
+            .lines()
+            .filter_map(EmojiHistory::parse_line)
+            .collect();
+        assert_eq!(read.len(), 2);
+        assert_eq!(
+            (read[0].code.as_str(), read[0].count, read[0].last_used),
+            ("👍🏽", 2.5, 1700000000)
+        );
+        assert_eq!(
+            (read[1].code.as_str(), read[1].count, read[1].last_used),
+            ("🎉", 1.0, 1600000000)
+        );
+    }
+}
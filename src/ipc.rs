@@ -0,0 +1,422 @@
+//! Only one instance of the picker runs at a time. It listens to a local socket
+//! (a named pipe on Windows), and the later invocations send it their command
+//! instead of starting another picker.
+
+use std::{
+    fmt,
+    io::{self, BufRead, BufReader, Write},
+    str::FromStr,
+};
+
+use clap::ValueEnum;
+
+use crate::shortcut::OpenGroup;
+
+/// A command sent to the running instance. On the socket, a command is a line:
+/// its name, then its arguments as `key=value`, separated by tabs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Show {
+        /// The group to open into, if any.
+        group: Option<OpenGroup>,
+        /// The text to filter the emojis with, if any.
+        query: Option<String>,
+    },
+    Hide,
+    Toggle,
+    Quit,
+    ReloadConfig,
+}
+
+impl Command {
+    /// Whether the command starts the picker when no instance is running.
+    pub fn starts_instance(&self) -> bool {
+        matches!(self, Command::Show { .. } | Command::Toggle)
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Command::Show { .. } => "show",
+            Command::Hide => "hide",
+            Command::Toggle => "toggle",
+            Command::Quit => "quit",
+            Command::ReloadConfig => "reload-config",
+        }
+    }
+}
+
+impl FromStr for Command {
+    type Err = IpcError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split('\t');
+        let name = parts.next().unwrap_or_default();
+        let mut command = match name {
+            "show" => Command::Show {
+                group: None,
+                query: None,
+            },
+            "hide" => Command::Hide,
+            "toggle" => Command::Toggle,
+            "quit" => Command::Quit,
+            "reload-config" => Command::ReloadConfig,
+            _ => return Err(IpcError::UnknownCommand(name.to_owned())),
+        };
+
+        for argument in parts {
+            let invalid = || IpcError::InvalidArgument(argument.to_owned());
+            let Command::Show { group, query } = &mut command else {
+                return Err(invalid());
+            };
+            match argument.split_once('=') {
+                Some(("group", value)) => {
+                    *group = Some(OpenGroup::from_str(value, false).map_err(|_| invalid())?)
+                }
+                Some(("query", value)) => *query = Some(value.to_owned()),
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(command)
+    }
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())?;
+        if let Command::Show { group, query } = self {
+            if let Some(group) = group.and_then(|g| g.to_possible_value()) {
+                write!(f, "\tgroup={}", group.get_name())?;
+            }
+            if let Some(query) = query {
+                // A query cannot span several arguments or lines.
+                write!(f, "\tquery={}", query.replace(['\t', '\n', '\r'], " "))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum IpcError {
+    #[error("Unknown command `{0}`")]
+    UnknownCommand(String),
+    #[error("Invalid argument `{0}`")]
+    InvalidArgument(String),
+    #[error("No instance of the picker is running.")]
+    NotRunning,
+    #[error("Another instance of the picker is running.")]
+    AlreadyRunning,
+    #[error("Failed to communicate with the other instance. Reason: {0}")]
+    Io(#[from] io::Error),
+    #[error("The running instance refused the command. Reason: {0}")]
+    Refused(String),
+}
+
+/// Sends a command to the running instance, and waits for it to be accepted.
+pub fn send(command: Command) -> Result<(), IpcError> {
+    let mut stream = platform::connect()?;
+    writeln!(stream, "{}", command)?;
+    stream.flush()?;
+
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply)?;
+    match reply.trim_end() {
+        "ok" => Ok(()),
+        reply => Err(IpcError::Refused(
+            reply.trim_start_matches("error: ").to_owned(),
+        )),
+    }
+}
+
+/// The socket of the running instance.
+pub struct Listener(platform::Listener);
+
+impl Listener {
+    /// Makes this process the running instance.
+    /// Fails with `IpcError::AlreadyRunning` if there is already one.
+    pub fn bind() -> Result<Self, IpcError> {
+        platform::Listener::bind().map(Self)
+    }
+
+    /// Accepts the commands of the other instances in a new thread.
+    pub fn serve(self, on_command: impl Fn(Command) + Send + 'static) {
+        let mut listener = self.0;
+        std::thread::spawn(move || loop {
+            let result = listener
+                .accept()
+                .and_then(|stream| reply(stream, &on_command));
+            if let Err(e) = result {
+                eprintln!("Failed to receive a command. Reason: {}", e);
+            }
+        });
+    }
+}
+
+/// Reads the command of a connection and replies whether it is accepted.
+fn reply(stream: platform::Stream, on_command: &impl Fn(Command)) -> io::Result<()> {
+    let mut line = String::new();
+    // Another instance checking whether this one runs sends nothing.
+    if BufReader::new(&stream).read_line(&mut line)? == 0 {
+        return Ok(());
+    }
+    let reply = match line.trim_end_matches(['\r', '\n']).parse() {
+        Ok(command) => {
+            on_command(command);
+            "ok".to_owned()
+        }
+        Err(e) => format!("error: {}", e),
+    };
+    let mut stream = &stream;
+    writeln!(stream, "{}", reply)?;
+    stream.flush()
+}
+
+#[cfg(unix)]
+mod platform {
+    use std::{
+        io,
+        os::unix::net::{UnixListener, UnixStream},
+        path::PathBuf,
+    };
+
+    use super::IpcError;
+
+    const SOCKET_FILE: &str = "emoji-picker.sock";
+
+    pub type Stream = UnixStream;
+
+    pub struct Listener {
+        listener: UnixListener,
+        path: PathBuf,
+    }
+
+    impl Listener {
+        pub fn bind() -> Result<Self, IpcError> {
+            let path = crate::paths::runtime_file(SOCKET_FILE)?;
+            if UnixStream::connect(&path).is_ok() {
+                return Err(IpcError::AlreadyRunning);
+            }
+            // Nobody listens to the socket: the instance that created it did not exit cleanly.
+            match std::fs::remove_file(&path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+                _ => {}
+            }
+            let listener = UnixListener::bind(&path)?;
+            Ok(Self { listener, path })
+        }
+
+        pub fn accept(&mut self) -> io::Result<Stream> {
+            self.listener.accept().map(|(stream, _)| stream)
+        }
+    }
+
+    impl Drop for Listener {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+
+    pub fn connect() -> Result<Stream, IpcError> {
+        let path = crate::paths::runtime_file(SOCKET_FILE)?;
+        UnixStream::connect(path).map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound | io::ErrorKind::ConnectionRefused => IpcError::NotRunning,
+            _ => e.into(),
+        })
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::{
+        fs::{File, OpenOptions},
+        io,
+        os::windows::io::{AsRawHandle, FromRawHandle},
+    };
+
+    use windows::{
+        core::HSTRING,
+        Win32::{
+            Foundation::{ERROR_ACCESS_DENIED, ERROR_PIPE_CONNECTED, HANDLE},
+            Storage::FileSystem::{FILE_FLAG_FIRST_PIPE_INSTANCE, PIPE_ACCESS_DUPLEX},
+            System::Pipes::{
+                ConnectNamedPipe, CreateNamedPipeW, PIPE_READMODE_BYTE, PIPE_REJECT_REMOTE_CLIENTS,
+                PIPE_TYPE_BYTE, PIPE_UNLIMITED_INSTANCES, PIPE_WAIT,
+            },
+        },
+    };
+
+    use super::IpcError;
+
+    const BUFFER_SIZE: u32 = 512;
+
+    pub type Stream = File;
+
+    /// A named pipe accepts one client per instance of the pipe, so a new one
+    /// is created for every connection.
+    pub struct Listener {
+        /// The instance created by `bind`, waiting for the first connection.
+        first: Option<File>,
+    }
+
+    impl Listener {
+        pub fn bind() -> Result<Self, IpcError> {
+            match create(true) {
+                Ok(pipe) => Ok(Self { first: Some(pipe) }),
+                Err(e) if e.raw_os_error() == Some(ERROR_ACCESS_DENIED.0 as i32) => {
+                    Err(IpcError::AlreadyRunning)
+                }
+                Err(e) => Err(e.into()),
+            }
+        }
+
+        pub fn accept(&mut self) -> io::Result<Stream> {
+            let pipe = match self.first.take() {
+                Some(pipe) => pipe,
+                None => create(false)?,
+            };
+            let handle = HANDLE(pipe.as_raw_handle());
+            match unsafe { ConnectNamedPipe(handle, None) } {
+                // The client connected between the creation of the pipe and this call.
+                Err(e) if e.code() == ERROR_PIPE_CONNECTED.to_hresult() => Ok(pipe),
+                Err(e) => Err(e.into()),
+                Ok(()) => Ok(pipe),
+            }
+        }
+    }
+
+    /// The pipe is named after the user, so that every session has its own picker.
+    fn pipe_name() -> String {
+        let user = std::env::var("USERNAME").unwrap_or_default();
+        format!(r"\\.\pipe\emoji-picker-{}", user)
+    }
+
+    /// Creates an instance of the pipe. Creating the first one fails
+    /// if another process owns the pipe.
+    fn create(first: bool) -> io::Result<File> {
+        let mut mode = PIPE_ACCESS_DUPLEX;
+        if first {
+            mode |= FILE_FLAG_FIRST_PIPE_INSTANCE;
+        }
+        let handle = unsafe {
+            CreateNamedPipeW(
+                &HSTRING::from(pipe_name()),
+                mode,
+                PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+                PIPE_UNLIMITED_INSTANCES,
+                BUFFER_SIZE,
+                BUFFER_SIZE,
+                0,
+                None,
+            )
+        };
+        if handle.is_invalid() {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(unsafe { File::from_raw_handle(handle.0) })
+    }
+
+    pub fn connect() -> Result<Stream, IpcError> {
+        OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(pipe_name())
+            .map_err(|e| match e.kind() {
+                io::ErrorKind::NotFound => IpcError::NotRunning,
+                _ => e.into(),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> Result<Command, IpcError> {
+        line.parse()
+    }
+
+    #[test]
+    fn reads_the_commands_it_writes() {
+        let commands = [
+            Command::Show {
+                group: None,
+                query: None,
+            },
+            Command::Show {
+                group: Some(OpenGroup::AnimalsAndNature),
+                query: Some("red heart".to_owned()),
+            },
+            Command::Show {
+                group: None,
+                query: Some("a=b".to_owned()),
+            },
+            Command::Hide,
+            Command::Toggle,
+            Command::Quit,
+            Command::ReloadConfig,
+        ];
+        for command in commands {
+            assert_eq!(parse(&command.to_string()).unwrap(), command);
+        }
+    }
+
+    #[test]
+    fn writes_the_arguments_after_the_name() {
+        let command = Command::Show {
+            group: Some(OpenGroup::RecentlyUsed),
+            query: Some("cat".to_owned()),
+        };
+        assert_eq!(command.to_string(), "show\tgroup=recently-used\tquery=cat");
+        assert_eq!(Command::ReloadConfig.to_string(), "reload-config");
+    }
+
+    #[test]
+    fn keeps_a_query_on_one_argument_and_one_line() {
+        let command = Command::Show {
+            group: None,
+            query: Some("thumbs\tup\r\nplease".to_owned()),
+        };
+        let line = command.to_string();
+        assert_eq!(line, "show\tquery=thumbs up  please");
+        assert_eq!(
+            parse(&line).unwrap(),
+            Command::Show {
+                group: None,
+                query: Some("thumbs up  please".to_owned()),
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_an_unknown_command() {
+        assert!(matches!(parse("open"), Err(IpcError::UnknownCommand(name)) if name == "open"));
+        assert!(matches!(parse(""), Err(IpcError::UnknownCommand(name)) if name.is_empty()));
+        assert!(matches!(parse("Show"), Err(IpcError::UnknownCommand(_))));
+    }
+
+    #[test]
+    fn rejects_an_argument_of_a_command_without_any() {
+        for line in ["hide\tgroup=flags", "quit\tquery=cat", "toggle\tnow"] {
+            assert!(
+                matches!(parse(line), Err(IpcError::InvalidArgument(_))),
+                "{line:?} was accepted"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_an_unknown_or_invalid_argument() {
+        assert!(matches!(
+            parse("show\tgroup=nowhere"),
+            Err(IpcError::InvalidArgument(argument)) if argument == "group=nowhere"
+        ));
+        assert!(matches!(
+            parse("show\tsize=big"),
+            Err(IpcError::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            parse("show\tcat"),
+            Err(IpcError::InvalidArgument(_))
+        ));
+    }
+}
@@ -0,0 +1,449 @@
+//! Picking an emoji with the keyboard: a cell of the grid is highlighted and
+//! moved with the arrows, while the filter keeps the keyboard for the text.
+//! The groups are laid out one after the other, in rows of the same width.
+
+use i_slint_backend_winit::winit::keyboard::{Key, ModifiersState, NamedKey};
+
+/// How the highlight moves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Move {
+    Left,
+    Right,
+    Up,
+    Down,
+    /// To the first emoji of the next group.
+    NextGroup,
+    /// To the first emoji of the previous group.
+    PreviousGroup,
+    /// To the first emoji of the row.
+    RowStart,
+    /// To the last emoji of the row.
+    RowEnd,
+    /// To the first emoji of the first group.
+    First,
+    /// To the last emoji of the last group.
+    Last,
+    /// Up by a page of rows.
+    PageUp,
+    /// Down by a page of rows.
+    PageDown,
+}
+
+/// An emoji of the grid: its group and its index in the group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cell {
+    pub group: usize,
+    pub index: usize,
+}
+
+/// The grid: how many emojis each group has, and how many fit in a row.
+#[derive(Debug, Clone, Copy)]
+pub struct Grid<'a> {
+    pub lengths: &'a [usize],
+    pub columns: usize,
+}
+
+impl Grid<'_> {
+    /// Returns the highlighted cell after the move, or None if the grid is empty.
+    /// Without any highlighted cell, or if it is not in the grid anymore,
+    /// the first emoji of the start group (or of the next group that has one)
+    /// is highlighted. A page is `page_rows` rows.
+    pub fn navigate(
+        &self,
+        from: Option<Cell>,
+        start: usize,
+        key_move: Move,
+        page_rows: usize,
+    ) -> Option<Cell> {
+        let Some(from) = from.filter(|cell| self.contains(*cell)) else {
+            return self
+                .group_from(start, 1)
+                .or_else(|| self.group_from(0, 1))
+                .map(|group| Cell { group, index: 0 });
+        };
+        let cell = match key_move {
+            Move::Left => self.left(from),
+            Move::Right => self.right(from),
+            Move::Up => self.up(from),
+            Move::Down => self.down(from),
+            Move::NextGroup => self.first_of(from.group + 1, 1),
+            Move::PreviousGroup => from
+                .group
+                .checked_sub(1)
+                .and_then(|group| self.first_of(group, -1)),
+            Move::RowStart => Some(Cell {
+                index: from.index - self.column(from),
+                ..from
+            }),
+            Move::RowEnd => Some(Cell {
+                index: (from.index - self.column(from) + self.columns() - 1)
+                    .min(self.lengths[from.group] - 1),
+                ..from
+            }),
+            Move::First => self.first_of(0, 1),
+            Move::Last => self
+                .group_from(self.lengths.len().checked_sub(1)?, -1)
+                .map(|group| Cell {
+                    group,
+                    index: self.lengths[group] - 1,
+                }),
+            Move::PageUp => Some(self.repeat(from, page_rows, Self::up)),
+            Move::PageDown => Some(self.repeat(from, page_rows, Self::down)),
+        };
+        // At the edges of the grid, the highlight stays.
+        cell.or(Some(from))
+    }
+
+    fn contains(&self, cell: Cell) -> bool {
+        self.lengths
+            .get(cell.group)
+            .is_some_and(|length| cell.index < *length)
+    }
+
+    fn columns(&self) -> usize {
+        self.columns.max(1)
+    }
+
+    fn column(&self, cell: Cell) -> usize {
+        cell.index % self.columns()
+    }
+
+    fn row(&self, cell: Cell) -> usize {
+        cell.index / self.columns()
+    }
+
+    fn last_row(&self, group: usize) -> usize {
+        (self.lengths[group] - 1) / self.columns()
+    }
+
+    /// Returns the first group having emojis, from the given one in the direction.
+    fn group_from(&self, group: usize, direction: isize) -> Option<usize> {
+        let mut group = group;
+        loop {
+            if *self.lengths.get(group)? > 0 {
+                return Some(group);
+            }
+            group = group.checked_add_signed(direction)?;
+        }
+    }
+
+    fn first_of(&self, group: usize, direction: isize) -> Option<Cell> {
+        self.group_from(group, direction)
+            .map(|group| Cell { group, index: 0 })
+    }
+
+    fn left(&self, from: Cell) -> Option<Cell> {
+        if from.index > 0 {
+            return Some(Cell {
+                index: from.index - 1,
+                ..from
+            });
+        }
+        let group = self.group_from(from.group.checked_sub(1)?, -1)?;
+        Some(Cell {
+            group,
+            index: self.lengths[group] - 1,
+        })
+    }
+
+    fn right(&self, from: Cell) -> Option<Cell> {
+        if from.index + 1 < self.lengths[from.group] {
+            return Some(Cell {
+                index: from.index + 1,
+                ..from
+            });
+        }
+        self.first_of(from.group + 1, 1)
+    }
+
+    /// Moves to the same column of the row above, in the last row of the
+    /// previous group when in the first one.
+    fn up(&self, from: Cell) -> Option<Cell> {
+        if self.row(from) > 0 {
+            return Some(Cell {
+                index: from.index - self.columns(),
+                ..from
+            });
+        }
+        let group = self.group_from(from.group.checked_sub(1)?, -1)?;
+        let row_start = self.last_row(group) * self.columns();
+        Some(Cell {
+            group,
+            index: (row_start + self.column(from)).min(self.lengths[group] - 1),
+        })
+    }
+
+    /// Moves to the same column of the row below, or to the last emoji if the
+    /// row below is shorter, in the first row of the next group when in the last one.
+    fn down(&self, from: Cell) -> Option<Cell> {
+        if self.row(from) < self.last_row(from.group) {
+            return Some(Cell {
+                index: (from.index + self.columns()).min(self.lengths[from.group] - 1),
+                ..from
+            });
+        }
+        let group = self.group_from(from.group + 1, 1)?;
+        Some(Cell {
+            group,
+            index: self.column(from).min(self.lengths[group] - 1),
+        })
+    }
+
+    fn repeat(&self, from: Cell, times: usize, step: fn(&Self, Cell) -> Option<Cell>) -> Cell {
+        let mut cell = from;
+        for _ in 0..times.max(1) {
+            match step(self, cell) {
+                Some(next) => cell = next,
+                None => break,
+            }
+        }
+        cell
+    }
+}
+
+/// Returns how the key moves the highlight, if it does while the filter has the keyboard.
+/// Up and Down always move it. Left, Right, Home and End move the caret of the filter until
+/// an emoji is highlighted. With Shift, the keys select the text of the filter.
+/// The keys the filter does not use (Tab, Page Up…) are left to the window.
+pub fn key_move(key: &Key, modifiers: ModifiersState, highlighted: bool) -> Option<Move> {
+    if modifiers.shift_key() || modifiers.alt_key() || modifiers.super_key() {
+        return None;
+    }
+    let control = modifiers.control_key();
+    let Key::Named(key) = key else {
+        return None;
+    };
+    match key {
+        NamedKey::ArrowUp => Some(Move::Up),
+        NamedKey::ArrowDown => Some(Move::Down),
+        _ if !highlighted => None,
+        NamedKey::ArrowLeft => Some(Move::Left),
+        NamedKey::ArrowRight => Some(Move::Right),
+        NamedKey::Home if control => Some(Move::First),
+        NamedKey::Home => Some(Move::RowStart),
+        NamedKey::End if control => Some(Move::Last),
+        NamedKey::End => Some(Move::RowEnd),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use i_slint_backend_winit::winit::keyboard::SmolStr;
+
+    use super::*;
+
+    /// Empty groups around groups of 5, 3 and 7 emojis, in rows of 3.
+    const LENGTHS: [usize; 6] = [0, 5, 0, 3, 7, 0];
+    const GRID: Grid = Grid {
+        lengths: &LENGTHS,
+        columns: 3,
+    };
+
+    fn cell(group: usize, index: usize) -> Option<Cell> {
+        Some(Cell { group, index })
+    }
+
+    /// Moves from the cell in the grid, by pages of 2 rows.
+    fn moved(group: usize, index: usize, key_move: Move) -> Option<Cell> {
+        GRID.navigate(cell(group, index), 0, key_move, 2)
+    }
+
+    #[test]
+    fn starts_at_the_first_emoji_of_the_start_group() {
+        assert_eq!(GRID.navigate(None, 0, Move::Down, 2), cell(1, 0));
+        assert_eq!(GRID.navigate(None, 2, Move::Right, 2), cell(3, 0));
+        assert_eq!(GRID.navigate(None, 4, Move::Up, 2), cell(4, 0));
+        // Only empty groups after the start one.
+        assert_eq!(GRID.navigate(None, 5, Move::Down, 2), cell(1, 0));
+        // The highlighted emoji was filtered out.
+        assert_eq!(GRID.navigate(cell(3, 5), 4, Move::Down, 2), cell(4, 0));
+        assert_eq!(GRID.navigate(cell(9, 0), 3, Move::Left, 2), cell(3, 0));
+
+        let empty = Grid {
+            lengths: &[0, 0],
+            columns: 3,
+        };
+        assert_eq!(empty.navigate(None, 0, Move::Down, 2), None);
+        assert_eq!(
+            Grid {
+                lengths: &[],
+                columns: 3
+            }
+            .navigate(None, 0, Move::Down, 2),
+            None
+        );
+    }
+
+    #[test]
+    fn moves_across_the_groups_skipping_the_empty_ones() {
+        assert_eq!(moved(1, 3, Move::Right), cell(1, 4));
+        assert_eq!(moved(1, 4, Move::Right), cell(3, 0));
+        assert_eq!(moved(3, 0, Move::Left), cell(1, 4));
+        assert_eq!(moved(3, 2, Move::Right), cell(4, 0));
+        assert_eq!(moved(4, 0, Move::Left), cell(3, 2));
+    }
+
+    #[test]
+    fn stays_at_the_edges_of_the_grid() {
+        for key_move in [Move::Left, Move::Up, Move::PreviousGroup, Move::PageUp] {
+            assert_eq!(moved(1, 0, key_move), cell(1, 0), "{key_move:?}");
+        }
+        for key_move in [Move::Right, Move::Down, Move::NextGroup, Move::PageDown] {
+            assert_eq!(moved(4, 6, key_move), cell(4, 6), "{key_move:?}");
+        }
+        // The first row has no row above, but the last one of the previous group.
+        assert_eq!(moved(1, 2, Move::Up), cell(1, 2));
+    }
+
+    #[test]
+    fn moves_down_to_the_short_last_rows() {
+        assert_eq!(moved(1, 1, Move::Down), cell(1, 4));
+        // No emoji below: the last one of the row below.
+        assert_eq!(moved(1, 2, Move::Down), cell(1, 4));
+        assert_eq!(moved(4, 5, Move::Down), cell(4, 6));
+        // From the last row, in the same column of the next group.
+        assert_eq!(moved(1, 3, Move::Down), cell(3, 0));
+        assert_eq!(moved(1, 4, Move::Down), cell(3, 1));
+        assert_eq!(moved(3, 2, Move::Down), cell(4, 2));
+    }
+
+    #[test]
+    fn moves_up_to_the_short_last_rows() {
+        assert_eq!(moved(1, 4, Move::Up), cell(1, 1));
+        assert_eq!(moved(4, 6, Move::Up), cell(4, 3));
+        // From the first row, in the same column of the last row of the previous group.
+        assert_eq!(moved(3, 1, Move::Up), cell(1, 4));
+        assert_eq!(moved(3, 2, Move::Up), cell(1, 4));
+        assert_eq!(moved(4, 2, Move::Up), cell(3, 2));
+    }
+
+    #[test]
+    fn moves_in_the_row() {
+        assert_eq!(moved(4, 4, Move::RowStart), cell(4, 3));
+        assert_eq!(moved(4, 3, Move::RowEnd), cell(4, 5));
+        assert_eq!(moved(1, 3, Move::RowEnd), cell(1, 4));
+        assert_eq!(moved(4, 6, Move::RowEnd), cell(4, 6));
+        assert_eq!(moved(4, 6, Move::RowStart), cell(4, 6));
+    }
+
+    #[test]
+    fn jumps_to_the_groups_and_the_ends() {
+        assert_eq!(moved(1, 3, Move::NextGroup), cell(3, 0));
+        assert_eq!(moved(3, 2, Move::PreviousGroup), cell(1, 0));
+        assert_eq!(moved(4, 4, Move::PreviousGroup), cell(3, 0));
+        assert_eq!(moved(4, 3, Move::First), cell(1, 0));
+        assert_eq!(moved(1, 3, Move::Last), cell(4, 6));
+    }
+
+    #[test]
+    fn moves_by_pages() {
+        assert_eq!(moved(1, 1, Move::PageDown), cell(3, 1));
+        assert_eq!(moved(4, 4, Move::PageUp), cell(3, 1));
+        // A page longer than the rest of the grid stops at its edge.
+        let page = |from, key_move| GRID.navigate(from, 0, key_move, 10);
+        assert_eq!(page(cell(3, 0), Move::PageDown), cell(4, 6));
+        assert_eq!(page(cell(4, 5), Move::PageUp), cell(1, 1));
+        // A page is at least a row.
+        assert_eq!(GRID.navigate(cell(1, 0), 0, Move::PageDown, 0), cell(1, 3));
+    }
+
+    #[test]
+    fn has_a_column_at_least() {
+        let grid = Grid {
+            lengths: &LENGTHS,
+            columns: 0,
+        };
+        assert_eq!(grid.navigate(cell(1, 0), 0, Move::Down, 2), cell(1, 1));
+        assert_eq!(grid.navigate(cell(1, 2), 0, Move::RowEnd, 2), cell(1, 2));
+    }
+
+    fn named(key: NamedKey) -> Key {
+        Key::Named(key)
+    }
+
+    #[test]
+    fn moves_up_and_down_without_a_highlight() {
+        for highlighted in [false, true] {
+            let key_move = |key| key_move(&named(key), ModifiersState::empty(), highlighted);
+            assert_eq!(key_move(NamedKey::ArrowUp), Some(Move::Up));
+            assert_eq!(key_move(NamedKey::ArrowDown), Some(Move::Down));
+        }
+    }
+
+    #[test]
+    fn leaves_the_caret_keys_to_the_filter_until_an_emoji_is_highlighted() {
+        let keys = [
+            (NamedKey::ArrowLeft, Move::Left),
+            (NamedKey::ArrowRight, Move::Right),
+            (NamedKey::Home, Move::RowStart),
+            (NamedKey::End, Move::RowEnd),
+        ];
+        for (key, expected) in keys {
+            assert_eq!(key_move(&named(key), ModifiersState::empty(), false), None);
+            let key_move = key_move(&named(key), ModifiersState::empty(), true);
+            assert_eq!(key_move, Some(expected), "{key:?}");
+        }
+    }
+
+    #[test]
+    fn moves_to_the_ends_of_the_grid_with_control() {
+        let control = ModifiersState::CONTROL;
+        assert_eq!(
+            key_move(&named(NamedKey::Home), control, true),
+            Some(Move::First)
+        );
+        assert_eq!(
+            key_move(&named(NamedKey::End), control, true),
+            Some(Move::Last)
+        );
+        assert_eq!(key_move(&named(NamedKey::Home), control, false), None);
+        assert_eq!(
+            key_move(&named(NamedKey::ArrowUp), control, false),
+            Some(Move::Up)
+        );
+    }
+
+    #[test]
+    fn leaves_the_keys_with_other_modifiers_to_the_filter() {
+        let modifiers = [
+            ModifiersState::SHIFT,
+            ModifiersState::ALT,
+            ModifiersState::SUPER,
+            ModifiersState::CONTROL | ModifiersState::SHIFT,
+        ];
+        for modifiers in modifiers {
+            for key in [
+                NamedKey::ArrowUp,
+                NamedKey::ArrowLeft,
+                NamedKey::Home,
+                NamedKey::End,
+            ] {
+                assert_eq!(
+                    key_move(&named(key), modifiers, true),
+                    None,
+                    "{modifiers:?} {key:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn leaves_the_other_keys_to_the_window() {
+        for highlighted in [false, true] {
+            for key in [
+                NamedKey::PageUp,
+                NamedKey::PageDown,
+                NamedKey::Tab,
+                NamedKey::Enter,
+            ] {
+                assert_eq!(
+                    key_move(&named(key), ModifiersState::empty(), highlighted),
+                    None
+                );
+            }
+            let key = Key::Character(SmolStr::new("a"));
+            assert_eq!(key_move(&key, ModifiersState::empty(), highlighted), None);
+        }
+    }
+}
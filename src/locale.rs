@@ -0,0 +1,272 @@
+use std::sync::{Mutex, RwLock};
+
+use crate::annotations;
+
+/// The language used for the emoji names, keywords and the user interface,
+/// as an ISO 639 code (e.g. "fr".) It is read for every emoji when searching,
+/// so it is a static string rather than cloned: see `intern`.
+static LANGUAGE: RwLock<&'static str> = RwLock::new(FALLBACK_LANGUAGE);
+
+/// The languages that were set without being bundled, leaked once each.
+static INTERNED: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+
+/// The language used when nothing is translated.
+pub const FALLBACK_LANGUAGE: &str = "en";
+
+/// Returns the language to use: the configured one, else the system's one.
+pub fn resolve(configured: Option<&str>) -> String {
+    configured
+        .map(String::from)
+        .or_else(sys_locale::get_locale)
+        .map(|locale| primary_language(&locale))
+        .filter(|language| !language.is_empty())
+        .unwrap_or_else(|| FALLBACK_LANGUAGE.to_owned())
+}
+
+/// Extracts the language of a locale, e.g. "fr" from "fr-CH" or "fr_CH.UTF-8".
+fn primary_language(locale: &str) -> String {
+    locale
+        .split(['-', '_', '.', '@'])
+        .next()
+        .unwrap_or_default()
+        .to_lowercase()
+}
+
+pub fn set_language(language: &str) {
+    let mut current = LANGUAGE.write().unwrap();
+    if *current != language {
+        *current = if language.is_empty() {
+            FALLBACK_LANGUAGE
+        } else {
+            intern(language)
+        };
+    }
+}
+
+/// Returns the language as a static string: the bundled one if there is one,
+/// else a copy leaked the first time the language is set, so that reloading
+/// the settings does not leak it again.
+fn intern(language: &str) -> &'static str {
+    if let Some((bundled, _)) = annotations::LANGUAGES.iter().find(|(l, _)| *l == language) {
+        return bundled;
+    }
+    let mut interned = INTERNED.lock().unwrap();
+    if let Some(known) = interned.iter().find(|l| **l == language) {
+        return known;
+    }
+    let leaked: &'static str = Box::leak(language.into());
+    interned.push(leaked);
+    leaked
+}
+
+pub fn language() -> &'static str {
+    *LANGUAGE.read().unwrap()
+}
+
+/// A text of the user interface.
+#[derive(Debug, Clone, Copy)]
+pub enum Text {
+    Group(emojis::Group),
+    Favorites,
+    BestMatches,
+    RecentlyUsed,
+    FilterPlaceholder,
+    Pin,
+    Unpin,
+    MoveEarlier,
+    MoveLater,
+}
+
+/// Returns the text in the current language, or in English if it is not translated.
+pub fn tr(text: Text) -> &'static str {
+    match language() {
+        "de" => german(text),
+        "es" => spanish(text),
+        "fr" => french(text),
+        "it" => italian(text),
+        "pt" => portuguese(text),
+        _ => english(text),
+    }
+}
+
+fn english(text: Text) -> &'static str {
+    use emojis::Group;
+    match text {
+        Text::Group(Group::Activities) => "Activities",
+        Text::Group(Group::AnimalsAndNature) => "Animals & Nature",
+        Text::Group(Group::Flags) => "Flags",
+        Text::Group(Group::FoodAndDrink) => "Food & Drink",
+        Text::Group(Group::Objects) => "Objects",
+        Text::Group(Group::PeopleAndBody) => "People & Body",
+        Text::Group(Group::SmileysAndEmotion) => "Smileys & Emotion",
+        Text::Group(Group::Symbols) => "Symbols",
+        Text::Group(Group::TravelAndPlaces) => "Travel & Places",
+        Text::Favorites => "Favorites",
+        Text::BestMatches => "Best matches",
+        Text::RecentlyUsed => "Recently used",
+        Text::FilterPlaceholder => "Filter emojis",
+        Text::Pin => "Pin to favorites",
+        Text::Unpin => "Unpin",
+        Text::MoveEarlier => "Move earlier",
+        Text::MoveLater => "Move later",
+    }
+}
+
+fn german(text: Text) -> &'static str {
+    use emojis::Group;
+    match text {
+        Text::Group(Group::Activities) => "Aktivitäten",
+        Text::Group(Group::AnimalsAndNature) => "Tiere & Natur",
+        Text::Group(Group::Flags) => "Flaggen",
+        Text::Group(Group::FoodAndDrink) => "Essen & Trinken",
+        Text::Group(Group::Objects) => "Objekte",
+        Text::Group(Group::PeopleAndBody) => "Menschen & Körper",
+        Text::Group(Group::SmileysAndEmotion) => "Smileys & Emotionen",
+        Text::Group(Group::Symbols) => "Symbole",
+        Text::Group(Group::TravelAndPlaces) => "Reisen & Orte",
+        Text::Favorites => "Favoriten",
+        Text::BestMatches => "Beste Treffer",
+        Text::RecentlyUsed => "Zuletzt verwendet",
+        Text::FilterPlaceholder => "Emojis filtern",
+        Text::Pin => "Zu Favoriten hinzufügen",
+        Text::Unpin => "Aus Favoriten entfernen",
+        Text::MoveEarlier => "Nach vorne",
+        Text::MoveLater => "Nach hinten",
+    }
+}
+
+fn spanish(text: Text) -> &'static str {
+    use emojis::Group;
+    match text {
+        Text::Group(Group::Activities) => "Actividades",
+        Text::Group(Group::AnimalsAndNature) => "Animales y naturaleza",
+        Text::Group(Group::Flags) => "Banderas",
+        Text::Group(Group::FoodAndDrink) => "Comida y bebida",
+        Text::Group(Group::Objects) => "Objetos",
+        Text::Group(Group::PeopleAndBody) => "Personas y cuerpo",
+        Text::Group(Group::SmileysAndEmotion) => "Caras y emociones",
+        Text::Group(Group::Symbols) => "Símbolos",
+        Text::Group(Group::TravelAndPlaces) => "Viajes y lugares",
+        Text::Favorites => "Favoritos",
+        Text::BestMatches => "Mejores resultados",
+        Text::RecentlyUsed => "Usados recientemente",
+        Text::FilterPlaceholder => "Filtrar emojis",
+        Text::Pin => "Añadir a favoritos",
+        Text::Unpin => "Quitar de favoritos",
+        Text::MoveEarlier => "Mover antes",
+        Text::MoveLater => "Mover después",
+    }
+}
+
+fn french(text: Text) -> &'static str {
+    use emojis::Group;
+    match text {
+        Text::Group(Group::Activities) => "Activités",
+        Text::Group(Group::AnimalsAndNature) => "Animaux et nature",
+        Text::Group(Group::Flags) => "Drapeaux",
+        Text::Group(Group::FoodAndDrink) => "Nourriture et boissons",
+        Text::Group(Group::Objects) => "Objets",
+        Text::Group(Group::PeopleAndBody) => "Personnes et corps",
+        Text::Group(Group::SmileysAndEmotion) => "Smileys et émotions",
+        Text::Group(Group::Symbols) => "Symboles",
+        Text::Group(Group::TravelAndPlaces) => "Voyages et lieux",
+        Text::Favorites => "Favoris",
+        Text::BestMatches => "Meilleurs résultats",
+        Text::RecentlyUsed => "Utilisés récemment",
+        Text::FilterPlaceholder => "Filtrer les emojis",
+        Text::Pin => "Ajouter aux favoris",
+        Text::Unpin => "Retirer des favoris",
+        Text::MoveEarlier => "Déplacer avant",
+        Text::MoveLater => "Déplacer après",
+    }
+}
+
+fn italian(text: Text) -> &'static str {
+    use emojis::Group;
+    match text {
+        Text::Group(Group::Activities) => "Attività",
+        Text::Group(Group::AnimalsAndNature) => "Animali e natura",
+        Text::Group(Group::Flags) => "Bandiere",
+        Text::Group(Group::FoodAndDrink) => "Cibo e bevande",
+        Text::Group(Group::Objects) => "Oggetti",
+        Text::Group(Group::PeopleAndBody) => "Persone e corpo",
+        Text::Group(Group::SmileysAndEmotion) => "Faccine ed emozioni",
+        Text::Group(Group::Symbols) => "Simboli",
+        Text::Group(Group::TravelAndPlaces) => "Viaggi e luoghi",
+        Text::Favorites => "Preferiti",
+        Text::BestMatches => "Risultati migliori",
+        Text::RecentlyUsed => "Usati di recente",
+        Text::FilterPlaceholder => "Filtra emoji",
+        Text::Pin => "Aggiungi ai preferiti",
+        Text::Unpin => "Rimuovi dai preferiti",
+        Text::MoveEarlier => "Sposta prima",
+        Text::MoveLater => "Sposta dopo",
+    }
+}
+
+fn portuguese(text: Text) -> &'static str {
+    use emojis::Group;
+    match text {
+        Text::Group(Group::Activities) => "Atividades",
+        Text::Group(Group::AnimalsAndNature) => "Animais e natureza",
+        Text::Group(Group::Flags) => "Bandeiras",
+        Text::Group(Group::FoodAndDrink) => "Comidas e bebidas",
+        Text::Group(Group::Objects) => "Objetos",
+        Text::Group(Group::PeopleAndBody) => "Pessoas e corpo",
+        Text::Group(Group::SmileysAndEmotion) => "Carinhas e emoções",
+        Text::Group(Group::Symbols) => "Símbolos",
+        Text::Group(Group::TravelAndPlaces) => "Viagens e lugares",
+        Text::Favorites => "Favoritos",
+        Text::BestMatches => "Melhores resultados",
+        Text::RecentlyUsed => "Usados recentemente",
+        Text::FilterPlaceholder => "Filtrar emojis",
+        Text::Pin => "Adicionar aos favoritos",
+        Text::Unpin => "Remover dos favoritos",
+        Text::MoveEarlier => "Mover para antes",
+        Text::MoveLater => "Mover para depois",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_the_language_of_a_locale() {
+        assert_eq!(primary_language("fr_FR.UTF-8"), "fr");
+        assert_eq!(primary_language("de-AT"), "de");
+        assert_eq!(primary_language("sr@latin"), "sr");
+        assert_eq!(primary_language("PT_br"), "pt");
+        assert_eq!(primary_language("it"), "it");
+    }
+
+    #[test]
+    fn prefers_the_configured_locale_to_the_system_one() {
+        assert_eq!(resolve(Some("de-AT")), "de");
+        assert_eq!(resolve(Some("fr_FR.UTF-8")), "fr");
+    }
+
+    #[test]
+    fn falls_back_to_english_for_an_empty_locale() {
+        assert_eq!(resolve(Some("")), FALLBACK_LANGUAGE);
+        assert_eq!(resolve(Some("_FR")), FALLBACK_LANGUAGE);
+    }
+
+    #[test]
+    fn uses_the_bundled_language_strings() {
+        let bundled = annotations::LANGUAGES
+            .iter()
+            .find(|(l, _)| *l == "en")
+            .unwrap()
+            .0;
+        assert!(std::ptr::eq(intern(&String::from("en")), bundled));
+    }
+
+    #[test]
+    fn leaks_an_unbundled_language_once() {
+        let first = intern(&String::from("tlh"));
+        let second = intern(&String::from("tlh"));
+        assert_eq!(first, "tlh");
+        assert!(std::ptr::eq(first, second));
+    }
+}
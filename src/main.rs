@@ -1,31 +1,107 @@
 #![windows_subsystem = "windows"]
 
+use cli::Outcome;
+use emoji_model::VecEmojiGroupModel;
+use favorites::Favorites;
 use handlers::{HandlerEvent, HandlerNotifyEvent, Handlers, NotifierReason};
+use history::EmojiHistory;
+use i_slint_backend_winit::winit::event::{ElementState, Ime, WindowEvent};
+use i_slint_backend_winit::winit::keyboard::ModifiersState;
+use i_slint_backend_winit::{WinitWindowAccessor as _, WinitWindowEventResult};
+use ipc::{Command, IpcError, Listener};
+use poller::Poller;
+use settings::{Settings, SettingsWatcher};
 use slint::{Model, ModelRc, Weak};
-use std::sync::{Arc, Mutex, RwLock};
+use state::State;
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock, RwLockReadGuard};
+use std::time::Duration;
 
+mod annotations;
+mod cli;
+mod clipboard;
 mod emoji;
 mod emoji_model;
+mod favorites;
 mod handler;
 mod handlers;
+mod history;
+mod ipc;
+mod keyboard;
+mod locale;
+mod output;
+mod paths;
+mod placement;
 mod poller;
+mod rules;
+mod search;
+mod settings;
+mod shortcut;
+mod state;
+#[cfg(target_os = "linux")]
+mod typing;
+mod window_info;
+#[cfg(test)]
+#[cfg(target_os = "linux")]
+mod xvfb;
 
 slint::include_modules!();
 
+/// Number of skin tones that can be selected, the default one included.
+const SKIN_TONES: i32 = 6;
+
+/// Interval between two checks of the settings file.
+const SETTINGS_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How the picker runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunMode {
+    /// Stays in the background until opened.
+    Background,
+    /// Opens on startup, then stays in the background.
+    Open,
+    /// Opens on startup, prints the selected emoji and exits.
+    Print,
+}
+
 type SharedApp = Arc<App>;
 struct App {
     ui: Arc<Mutex<Weak<EmojiPickerWindow>>>,
     open_source: RwLock<NotifierReason>,
+    history: Mutex<EmojiHistory>,
+    favorites: Mutex<Favorites>,
+    settings: RwLock<Settings>,
+    run_mode: RunMode,
+    /// Whether the selected emoji was printed, in the print mode.
+    printed: AtomicBool,
 }
 
 impl App {
-    pub fn new(ui: Weak<EmojiPickerWindow>) -> Self {
+    pub fn new(ui: Weak<EmojiPickerWindow>, settings: Settings, run_mode: RunMode) -> Self {
         Self {
             ui: Arc::new(Mutex::new(ui)),
             open_source: RwLock::new(NotifierReason::None),
+            history: Mutex::new(EmojiHistory::load()),
+            favorites: Mutex::new(Favorites::load()),
+            settings: RwLock::new(settings),
+            run_mode,
+            printed: AtomicBool::new(false),
         }
     }
 
+    pub fn run_mode(&self) -> RunMode {
+        self.run_mode
+    }
+
+    pub fn set_printed(&self) {
+        self.printed.store(true, Ordering::Release);
+    }
+
+    pub fn printed(&self) -> bool {
+        self.printed.load(Ordering::Acquire)
+    }
+
     pub fn weak_ui(&self) -> Weak<EmojiPickerWindow> {
         self.ui.lock().unwrap().clone()
     }
@@ -37,14 +113,91 @@ impl App {
     pub fn get_reason(&self) -> NotifierReason {
         *self.open_source.read().unwrap()
     }
+
+    pub fn history(&self) -> MutexGuard<'_, EmojiHistory> {
+        self.history.lock().unwrap()
+    }
+
+    pub fn favorites(&self) -> MutexGuard<'_, Favorites> {
+        self.favorites.lock().unwrap()
+    }
+
+    pub fn settings(&self) -> RwLockReadGuard<'_, Settings> {
+        self.settings.read().unwrap()
+    }
 }
 
-fn main() {
+fn main() -> ExitCode {
+    let (command, run_mode) = match cli::parse().command.unwrap_or_default().run() {
+        Outcome::Picker(None) => (None, RunMode::Background),
+        Outcome::Picker(Some(command)) => (Some(command), RunMode::Open),
+        Outcome::Print(command) => (Some(command), RunMode::Print),
+        Outcome::Exit(code) => return code,
+    };
+    // A printing picker is not the running instance: it only serves its caller.
+    let listener = if run_mode == RunMode::Print {
+        None
+    } else {
+        match Listener::bind() {
+            Ok(listener) => Some(listener),
+            Err(IpcError::AlreadyRunning) => {
+                let sent = match command {
+                    Some(command) => ipc::send(command),
+                    None => Err(IpcError::AlreadyRunning),
+                };
+                if let Err(e) = sent {
+                    eprintln!("{}", e);
+                    return ExitCode::FAILURE;
+                }
+                return ExitCode::SUCCESS;
+            }
+            Err(e) => {
+                eprintln!(
+                    "{} Other instances will not be able to control this one.",
+                    e
+                );
+                None
+            }
+        }
+    };
+    if command
+        .as_ref()
+        .is_some_and(|command| !command.starts_instance())
+    {
+        eprintln!("{}", IpcError::NotRunning);
+        return ExitCode::FAILURE;
+    }
+
+    let settings = Settings::load().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        Settings::default()
+    });
+    locale::set_language(&locale::resolve(settings.locale.as_deref()));
+    emoji::set_image_dir(&settings.image_dir);
+    shortcut::set_bindings(settings.shortcuts.clone());
+    #[cfg(target_os = "linux")]
+    typing::set_backend(settings.typing_backend);
+
     let ui = EmojiPickerWindow::new().expect("Failed to create window.");
-    let app = Arc::new(App::new(ui.as_weak()));
-    let handlers = Arc::new(Handlers::new(&ui));
+    let handlers = Arc::new(Handlers::new(&ui, &settings, listener, run_mode));
+    let app = Arc::new(App::new(ui.as_weak(), settings, run_mode));
 
-    init_emojis(&ui);
+    apply_ui_settings(&ui, &app.settings());
+    init_emojis(&ui, app.clone());
+    init_keyboard(&ui);
+    // The list only scrolls once the window is shown: the group is displayed on the first open.
+    let mut first_group = Some(restore_state(&ui));
+    if let Some(Command::Show { group, query }) = command {
+        if let Some(group) = group {
+            first_group = Some(emoji_model::group_index(group) as i32);
+        }
+        if let Some(query) = query {
+            // The best matches of the query are displayed instead.
+            first_group = None;
+            ui.invoke_set_filter(query.into());
+        }
+    }
+    let settings_watcher = watch_settings(app.clone(), handlers.clone());
 
     // Setup emoji selected
     ui.on_emoji_selected({
@@ -54,11 +207,25 @@ fn main() {
         }
     });
 
+    // An emoji accepted with Enter is selected, then the window closes as the reason says
+    ui.on_emoji_accepted({
+        let (app, ui, handlers) = (app.clone(), ui.as_weak(), handlers.clone());
+        move |emoji| {
+            handlers.trigger(HandlerEvent::EmojiSelected(&(app.clone(), emoji.into())));
+            if app.get_reason().closes_on_accept() {
+                ui.unwrap().invoke_close_requested();
+            }
+        }
+    });
+
     // Setup close handlers
     ui.window().on_close_requested({
         let (app, handlers) = (app.clone(), handlers.clone());
         move || {
             handlers.trigger(HandlerEvent::Close(&(app.clone(), NotifierReason::None)));
+            if app.run_mode() == RunMode::Print {
+                slint::quit_event_loop().expect("Failed to quit the event loop.");
+            }
             slint::CloseRequestResponse::HideWindow
         }
     });
@@ -66,13 +233,18 @@ fn main() {
     // Caller to open a window and call the open handlers
     let open_window = {
         let (app, ui, handlers) = (app.clone(), ui.as_weak(), handlers.clone());
+        let first_group = Arc::new(Mutex::new(first_group));
         move |reason: NotifierReason| {
             app.set_reason(reason);
             handlers.trigger(HandlerEvent::BeforeOpen(&(app.clone(), reason)));
             ui.upgrade_in_event_loop({
-                let (handlers, app) = (handlers.clone(), app.clone());
+                let (handlers, app, first_group) =
+                    (handlers.clone(), app.clone(), first_group.clone());
                 move |ui| {
                     ui.window().show().expect("Failed to show window.");
+                    if let Some(group) = first_group.lock().unwrap().take() {
+                        ui.invoke_scroll_to_group(group);
+                    }
                     handlers.trigger(HandlerEvent::Open(&(app, reason)));
                 }
             })
@@ -81,12 +253,17 @@ fn main() {
     };
 
     let close_window = {
-        let (ui, handlers) = (ui.as_weak(), handlers.clone());
+        let (app, ui, handlers) = (app.clone(), ui.as_weak(), handlers.clone());
         move |reason| {
             let handlers = handlers.clone();
             handlers.trigger(HandlerEvent::Close(&(app.clone(), reason)));
+            // Closing a printing picker cancels it.
+            let quit = reason == NotifierReason::Quit || app.run_mode() == RunMode::Print;
             ui.upgrade_in_event_loop(move |app| {
                 app.window().hide().expect("Failed to hide window.");
+                if quit {
+                    slint::quit_event_loop().expect("Failed to quit the event loop.");
+                }
             })
             .unwrap();
         }
@@ -103,21 +280,241 @@ fn main() {
     // This is not really necessary.
     poller_for_open.signal_stop();
     poller_for_close.signal_stop();
+    settings_watcher.signal_stop();
+
+    if run_mode == RunMode::Print && !app.printed() {
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+/// Applies the settings that are properties of the user interface.
+fn apply_ui_settings(ui: &EmojiPickerWindow, settings: &Settings) {
+    let sizes = ui.global::<Sizes>();
+    sizes.set_button_width(settings.button_width);
+    sizes.set_button_height(settings.button_height);
+    ui.invoke_set_theme(match settings.theme {
+        settings::Theme::System => Theme::System,
+        settings::Theme::Light => Theme::Light,
+        settings::Theme::Dark => Theme::Dark,
+    });
+}
+
+/// Reloads the settings when their file changes.
+/// If the new settings are invalid, the previous ones are kept.
+fn watch_settings(app: SharedApp, handlers: Arc<Handlers<'static>>) -> Poller {
+    let mut watcher = SettingsWatcher::new();
+    Poller::new(SETTINGS_POLL_INTERVAL, move || match watcher.poll() {
+        Some(Ok(settings)) => reload_settings(&app, &handlers, settings),
+        Some(Err(e)) => eprintln!("{} Keeping the previous settings.", e),
+        None => {}
+    })
+}
+
+/// Replaces the settings of the app and applies the changes.
+/// The output mode is read on every selection, and the shortcuts on every key press,
+/// so they need nothing more.
+fn reload_settings(app: &SharedApp, handlers: &Handlers, settings: Settings) {
+    let previous = std::mem::replace(&mut *app.settings.write().unwrap(), settings);
+    let settings = app.settings();
+    handlers.apply_settings(&settings);
+
+    let language = locale::resolve(settings.locale.as_deref());
+    let reload_emojis = language != locale::language() || previous.image_dir != settings.image_dir;
+    locale::set_language(&language);
+    emoji::set_image_dir(&settings.image_dir);
+    shortcut::set_bindings(settings.shortcuts.clone());
+    #[cfg(target_os = "linux")]
+    typing::set_backend(settings.typing_backend);
+
+    let _ = app.weak_ui().upgrade_in_event_loop({
+        let app = app.clone();
+        move |ui| {
+            apply_ui_settings(&ui, &app.settings());
+            if reload_emojis {
+                load_emojis(&ui, &app);
+                ui.invoke_refresh_filter();
+            }
+        }
+    });
+}
+
+/// Restores the skin tone and the size of the window of the last run.
+/// Returns the group that was displayed, to scroll to once the window is shown.
+fn restore_state(ui: &EmojiPickerWindow) -> i32 {
+    let state = State::load();
+    ui.set_selected_skin_tone(state.skin_tone.clamp(0, SKIN_TONES - 1));
+    if let Some((width, height)) = state.window_size.filter(|(w, h)| *w > 0. && *h > 0.) {
+        ui.window().set_size(slint::LogicalSize::new(width, height));
+    }
+    if let Some((x, y)) = state.window_position {
+        ui.window().set_position(slint::PhysicalPosition::new(x, y));
+    }
+    state.group.max(0)
+}
+
+/// Creates the emoji groups and sets the texts of the user interface,
+/// in the current language.
+fn load_emojis(ui: &EmojiPickerWindow, app: &App) {
+    let model = VecEmojiGroupModel::new(&app.favorites(), &app.history());
+    ui.set_emoji_groups(ModelRc::new(model));
+    ui.set_filter_placeholder(locale::tr(locale::Text::FilterPlaceholder).into());
+
+    let labels = ui.global::<Labels>();
+    labels.set_pin(locale::tr(locale::Text::Pin).into());
+    labels.set_unpin(locale::tr(locale::Text::Unpin).into());
+    labels.set_move_earlier(locale::tr(locale::Text::MoveEarlier).into());
+    labels.set_move_later(locale::tr(locale::Text::MoveLater).into());
 }
 
 /// This function initializes the emoji buttons in the app.
-/// It also sets up the filter function to filter the emojis
-fn init_emojis(app: &EmojiPickerWindow) {
-    let model = emoji_model::VecEmojiGroupModel::new();
-    let model = ModelRc::new(model);
-
-    app.set_emoji_groups(model.clone());
-
-    app.on_filter(move |s| {
-        model
-            .as_any()
-            .downcast_ref::<emoji_model::VecEmojiGroupModel>()
-            .unwrap()
-            .filter(s.into());
+/// It also sets up the filter function to filter the emojis, its best match
+/// and the actions of the favorites' context menu.
+/// As the emojis can be loaded again, the model is always read from the window.
+fn init_emojis(ui: &EmojiPickerWindow, app: SharedApp) {
+    load_emojis(ui, &app);
+
+    ui.on_filter({
+        let (app, ui) = (app.clone(), ui.as_weak());
+        move |s| {
+            let groups = ui.unwrap().get_emoji_groups();
+            if let Some(model) = groups.as_any().downcast_ref::<VecEmojiGroupModel>() {
+                model.filter(s.into(), &app.history());
+            }
+        }
+    });
+
+    ui.on_first_match({
+        let ui = ui.as_weak();
+        move || {
+            let groups = ui.unwrap().get_emoji_groups();
+            groups
+                .as_any()
+                .downcast_ref::<VecEmojiGroupModel>()
+                .and_then(|model| model.first_match())
+                .unwrap_or_default()
+        }
     });
+
+    ui.on_favorite_action({
+        let ui = ui.as_weak();
+        move |action, code| {
+            let favorites = {
+                let mut favorites = app.favorites();
+                match action {
+                    FavoriteAction::Pin => favorites.pin(&code),
+                    FavoriteAction::Unpin => favorites.unpin(&code),
+                    FavoriteAction::MoveEarlier => favorites.move_by(&code, -1),
+                    FavoriteAction::MoveLater => favorites.move_by(&code, 1),
+                }
+                if let Err(e) = favorites.save() {
+                    eprintln!("Failed to save the favorites. Reason: {}", e);
+                }
+                favorites.emojis().collect()
+            };
+            let groups = ui.unwrap().get_emoji_groups();
+            if let Some(model) = groups.as_any().downcast_ref::<VecEmojiGroupModel>() {
+                model.set_favorites(favorites);
+            }
+        }
+    });
+}
+
+/// Lets the keyboard move the highlighted emoji through the groups, while typing in the filter.
+fn init_keyboard(ui: &EmojiPickerWindow) {
+    ui.on_move_highlight({
+        let ui = ui.as_weak();
+        move |key_move, columns, page_rows| {
+            let ui = ui.unwrap();
+            let lengths: Vec<usize> = ui
+                .get_emoji_groups()
+                .iter()
+                .map(|group| group.emojis.row_count())
+                .collect();
+            let grid = keyboard::Grid {
+                lengths: &lengths,
+                columns: columns.max(1) as usize,
+            };
+            let from =
+                (ui.get_highlighted_group() >= 0 && ui.get_highlighted_index() >= 0).then(|| {
+                    keyboard::Cell {
+                        group: ui.get_highlighted_group() as usize,
+                        index: ui.get_highlighted_index() as usize,
+                    }
+                });
+            let start = ui.invoke_current_group().max(0) as usize;
+            let key_move = keyboard::Move::from(key_move);
+            if let Some(cell) = grid.navigate(from, start, key_move, page_rows.max(1) as usize) {
+                ui.set_highlighted_group(cell.group as i32);
+                ui.set_highlighted_index(cell.index as i32);
+                ui.invoke_scroll_to_highlight();
+            }
+        }
+    });
+
+    // The filter keeps the keyboard: the keys moving the highlight are taken before it gets them.
+    // The keys redirected by the hook (`key-redir`) are posted to the window as WM_KEYDOWN and
+    // WM_KEYUP, which winit turns into the same events.
+    ui.window().on_winit_window_event({
+        let ui = ui.as_weak();
+        let mut modifiers = ModifiersState::empty();
+        let mut composing = false;
+        move |_, event| {
+            match event {
+                WindowEvent::ModifiersChanged(new) => modifiers = new.state(),
+                // The keys belong to the input method while it composes a text.
+                WindowEvent::Ime(Ime::Preedit(text, _)) => composing = !text.is_empty(),
+                WindowEvent::Ime(Ime::Commit(_) | Ime::Disabled) => composing = false,
+                WindowEvent::KeyboardInput { event, .. }
+                    if event.state == ElementState::Pressed && !composing =>
+                {
+                    let ui = ui.unwrap();
+                    let highlighted = ui.get_highlighted_group() >= 0;
+                    let key_move = keyboard::key_move(&event.logical_key, modifiers, highlighted);
+                    if let Some(key_move) = key_move {
+                        ui.invoke_navigate(key_move.into());
+                        return WinitWindowEventResult::PreventDefault;
+                    }
+                }
+                _ => {}
+            }
+            WinitWindowEventResult::Propagate
+        }
+    });
+}
+
+/// The moves of the highlight are the same in the UI.
+macro_rules! convert_moves {
+    ($($variant:ident),*) => {
+        impl From<GridMove> for keyboard::Move {
+            fn from(key_move: GridMove) -> Self {
+                match key_move {
+                    $(GridMove::$variant => keyboard::Move::$variant,)*
+                }
+            }
+        }
+
+        impl From<keyboard::Move> for GridMove {
+            fn from(key_move: keyboard::Move) -> Self {
+                match key_move {
+                    $(keyboard::Move::$variant => GridMove::$variant,)*
+                }
+            }
+        }
+    };
 }
+
+convert_moves!(
+    Left,
+    Right,
+    Up,
+    Down,
+    NextGroup,
+    PreviousGroup,
+    RowStart,
+    RowEnd,
+    First,
+    Last,
+    PageUp,
+    PageDown
+);
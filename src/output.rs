@@ -0,0 +1,315 @@
+//! Where the selected emoji goes: typed in the focused window, copied to the
+//! clipboard, printed or appended to a file.
+
+use std::{
+    fmt::Debug,
+    fs::OpenOptions,
+    io::{self, Write},
+    path::PathBuf,
+    time::Duration,
+};
+
+use crate::clipboard::{Clipboard, ClipboardError, SystemClipboard};
+
+#[derive(Debug, thiserror::Error)]
+pub enum OutputError {
+    #[error("Failed to write the emoji. Reason: {0}")]
+    Io(#[from] io::Error),
+    #[error("Failed to type the emoji. Reason: {0}")]
+    Type(String),
+    #[error("Failed to copy the emoji to the clipboard. Reason: {0}")]
+    Clipboard(#[from] ClipboardError),
+    #[cfg(not(any(windows, target_os = "linux")))]
+    #[error("{0} is not supported on this platform.")]
+    Unsupported(&'static str),
+}
+
+/// Receives the selected emojis.
+/// The sinks tell what they are with Debug, e.g. `FallbackSink(TypeSink, ClipboardSink)`.
+pub trait OutputSink: Send + Sync + Debug {
+    fn output(&self, emoji: &str) -> Result<(), OutputError>;
+
+    /// Whether the emoji is sent to the focused window, which must be the one it goes to.
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))] // The picker takes the focus on Linux
+    fn needs_focus(&self) -> bool {
+        false
+    }
+}
+
+/// Types the emoji in the focused window.
+#[derive(Debug)]
+pub struct TypeSink;
+
+impl OutputSink for TypeSink {
+    fn output(&self, emoji: &str) -> Result<(), OutputError> {
+        platform::type_text(emoji)
+    }
+
+    fn needs_focus(&self) -> bool {
+        true
+    }
+}
+
+/// Pastes the emoji in the focused window: copies it, presses Ctrl+V,
+/// then restores the previous content of the clipboard.
+/// Some applications (remote desktops, some Electron ones) ignore the typed emojis.
+///
+/// Only the formats whose data can be copied are restored. On Windows, the ones
+/// holding a handle are dropped: the bitmaps (restored from their DIB format if
+/// the application set it), metafiles, palettes and owner-drawn content. On X11,
+/// the formats sent in several pieces (INCR), i.e. the large ones, are dropped.
+#[derive(Debug)]
+pub struct PasteSink;
+
+/// How long the application has to read the clipboard before it is restored.
+const RESTORE_DELAY: Duration = Duration::from_millis(500);
+
+impl OutputSink for PasteSink {
+    fn output(&self, emoji: &str) -> Result<(), OutputError> {
+        let saved = SystemClipboard.save();
+        SystemClipboard.set_text(emoji)?;
+        if let Err(e) = platform::paste() {
+            // Nothing reads the emoji, the previous content is put back right away.
+            if let Ok(saved) = &saved {
+                if let Err(e) = SystemClipboard.restore(saved) {
+                    eprintln!("Failed to restore the clipboard. Reason: {}", e);
+                }
+            }
+            return Err(e);
+        }
+
+        match saved {
+            // The application reads the clipboard when it handles Ctrl+V, later on.
+            Ok(saved) => {
+                std::thread::spawn(move || {
+                    std::thread::sleep(RESTORE_DELAY);
+                    if let Err(e) = SystemClipboard.restore(&saved) {
+                        eprintln!("Failed to restore the clipboard. Reason: {}", e);
+                    }
+                });
+            }
+            Err(e) => eprintln!(
+                "Failed to save the clipboard, it is not restored. Reason: {}",
+                e
+            ),
+        }
+        Ok(())
+    }
+
+    fn needs_focus(&self) -> bool {
+        true
+    }
+}
+
+/// Copies the emoji to the clipboard.
+#[derive(Debug)]
+pub struct ClipboardSink;
+
+impl OutputSink for ClipboardSink {
+    fn output(&self, emoji: &str) -> Result<(), OutputError> {
+        Ok(SystemClipboard.set_text(emoji)?)
+    }
+}
+
+/// Outputs to the first sink, or to the second one if the first fails
+/// (e.g. typing, else copying.)
+#[derive(Debug)]
+pub struct FallbackSink<First, Second>(pub First, pub Second);
+
+impl<First: OutputSink, Second: OutputSink> OutputSink for FallbackSink<First, Second> {
+    fn output(&self, emoji: &str) -> Result<(), OutputError> {
+        self.0.output(emoji).or_else(|e| {
+            eprintln!("{} Trying another output.", e);
+            self.1.output(emoji)
+        })
+    }
+
+    fn needs_focus(&self) -> bool {
+        self.0.needs_focus() || self.1.needs_focus()
+    }
+}
+
+impl<Sink: OutputSink + ?Sized> OutputSink for Box<Sink> {
+    fn output(&self, emoji: &str) -> Result<(), OutputError> {
+        (**self).output(emoji)
+    }
+
+    fn needs_focus(&self) -> bool {
+        (**self).needs_focus()
+    }
+}
+
+/// Prints the emoji on its own line.
+#[derive(Debug)]
+pub struct StdoutSink;
+
+impl OutputSink for StdoutSink {
+    fn output(&self, emoji: &str) -> Result<(), OutputError> {
+        let mut stdout = io::stdout().lock();
+        writeln!(stdout, "{}", emoji)?;
+        stdout.flush()?;
+        Ok(())
+    }
+}
+
+/// Appends the emoji to a file, on its own line.
+#[derive(Debug)]
+pub struct FileSink(pub PathBuf);
+
+impl OutputSink for FileSink {
+    fn output(&self, emoji: &str) -> Result<(), OutputError> {
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.0)?;
+        writeln!(file, "{}", emoji)?;
+        Ok(())
+    }
+}
+
+/// Keeps the emojis instead of outputting them, to check which ones were output.
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub struct RecordingSink(std::sync::Mutex<Vec<String>>);
+
+#[cfg(test)]
+impl RecordingSink {
+    pub fn recorded(&self) -> Vec<String> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+impl OutputSink for RecordingSink {
+    fn output(&self, emoji: &str) -> Result<(), OutputError> {
+        self.0.lock().unwrap().push(emoji.to_owned());
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use windows::Win32::UI::{
+        Input::KeyboardAndMouse::{
+            SendInput, INPUT, INPUT_0, INPUT_KEYBOARD, KEYBDINPUT, KEYBD_EVENT_FLAGS,
+            KEYEVENTF_KEYUP, KEYEVENTF_UNICODE, VIRTUAL_KEY, VK_CONTROL, VK_V,
+        },
+        WindowsAndMessaging::GetMessageExtraInfo,
+    };
+
+    use super::OutputError;
+
+    /// Types the given String as Unicode characters.
+    pub fn type_text(code: &str) -> Result<(), OutputError> {
+        let encoded = str::encode_utf16(code);
+        let extra_info = unsafe { GetMessageExtraInfo() };
+        let input_struct_kd = encoded.into_iter().map(|c| INPUT {
+            r#type: INPUT_KEYBOARD,
+            Anonymous: INPUT_0 {
+                ki: KEYBDINPUT {
+                    dwExtraInfo: extra_info.0 as usize,
+                    wVk: VIRTUAL_KEY(0),
+                    wScan: c,
+                    dwFlags: KEYEVENTF_UNICODE,
+                    time: 0,
+                },
+            },
+        });
+        let input_struct_kf = input_struct_kd.clone().map(|mut k| {
+            unsafe {
+                k.Anonymous.ki.dwFlags |= KEYEVENTF_KEYUP;
+            }
+            k
+        });
+        let input_struct = input_struct_kd.chain(input_struct_kf).collect::<Vec<_>>();
+        send(&input_struct)
+    }
+
+    /// Presses Ctrl+V.
+    pub fn paste() -> Result<(), OutputError> {
+        let key = |key: VIRTUAL_KEY, flags: KEYBD_EVENT_FLAGS| INPUT {
+            r#type: INPUT_KEYBOARD,
+            Anonymous: INPUT_0 {
+                ki: KEYBDINPUT {
+                    wVk: key,
+                    dwFlags: flags,
+                    ..Default::default()
+                },
+            },
+        };
+        send(&[
+            key(VK_CONTROL, KEYBD_EVENT_FLAGS(0)),
+            key(VK_V, KEYBD_EVENT_FLAGS(0)),
+            key(VK_V, KEYEVENTF_KEYUP),
+            key(VK_CONTROL, KEYEVENTF_KEYUP),
+        ])
+    }
+
+    fn send(inputs: &[INPUT]) -> Result<(), OutputError> {
+        let sent = unsafe { SendInput(inputs, std::mem::size_of::<INPUT>() as i32) };
+        if sent as usize != inputs.len() {
+            // The input is blocked by another thread, or by the UIPI.
+            return Err(OutputError::Type(
+                windows::core::Error::from_win32().to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::OutputError;
+    use crate::typing;
+
+    pub fn type_text(code: &str) -> Result<(), OutputError> {
+        typing::type_text(code).map_err(|e| OutputError::Type(e.to_string()))
+    }
+
+    pub fn paste() -> Result<(), OutputError> {
+        typing::paste().map_err(|e| OutputError::Type(e.to_string()))
+    }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+mod platform {
+    use super::OutputError;
+
+    pub fn type_text(_: &str) -> Result<(), OutputError> {
+        Err(OutputError::Unsupported("Typing"))
+    }
+
+    pub fn paste() -> Result<(), OutputError> {
+        Err(OutputError::Unsupported("Pasting"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Fails to output anything.
+    #[derive(Debug)]
+    struct FailingSink;
+
+    impl OutputSink for FailingSink {
+        fn output(&self, _: &str) -> Result<(), OutputError> {
+            Err(OutputError::Type("no window".to_owned()))
+        }
+    }
+
+    #[test]
+    fn fallback_keeps_the_first_sink_while_it_works() {
+        let sink = FallbackSink(RecordingSink::default(), RecordingSink::default());
+        sink.output("👍").unwrap();
+        assert_eq!(sink.0.recorded(), ["👍"]);
+        assert!(sink.1.recorded().is_empty());
+    }
+
+    #[test]
+    fn fallback_uses_the_second_sink_when_the_first_fails() {
+        let sink = FallbackSink(FailingSink, RecordingSink::default());
+        sink.output("👍").unwrap();
+        assert_eq!(sink.1.recorded(), ["👍"]);
+
+        let sink = FallbackSink(FailingSink, FailingSink);
+        assert!(matches!(sink.output("👍"), Err(OutputError::Type(_))));
+    }
+}
@@ -0,0 +1,66 @@
+use std::path::PathBuf;
+#[cfg(unix)]
+use std::{fs, io};
+
+/// Name of the directory used by the application in the platform specific folders.
+const APP_DIR: &str = "emoji-picker";
+
+/// Returns the path of a file stored in the user's data directory
+/// (e.g. `%APPDATA%\emoji-picker` or `~/.local/share/emoji-picker`.)
+pub fn data_file(name: &str) -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join(APP_DIR).join(name))
+}
+
+/// Returns the path of a file stored in the user's config directory
+/// (e.g. `%APPDATA%\emoji-picker` or `~/.config/emoji-picker`.)
+pub fn config_file(name: &str) -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join(APP_DIR).join(name))
+}
+
+/// Returns the path of a file stored in the user's runtime directory
+/// (e.g. `/run/user/1000`), or in a private directory of the temporary one if there is none.
+#[cfg(unix)]
+pub fn runtime_file(name: &str) -> io::Result<PathBuf> {
+    match dirs::runtime_dir() {
+        Some(dir) => Ok(dir.join(name)),
+        None => Ok(private_temp_dir()?.join(name)),
+    }
+}
+
+/// Returns a directory of the temporary directory that only the user can access, creating it
+/// if needed. The temporary directory is shared with the other users: one of them could have
+/// created the directory first, so its owner and permissions are checked.
+#[cfg(unix)]
+fn private_temp_dir() -> io::Result<PathBuf> {
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+
+    let uid = unsafe { libc::getuid() };
+    let dir = std::env::temp_dir().join(format!("{}-{}", APP_DIR, uid));
+    match fs::DirBuilder::new().mode(0o700).create(&dir) {
+        Err(e) if e.kind() != io::ErrorKind::AlreadyExists => return Err(e),
+        _ => {}
+    }
+    // A symbolic link is not followed: it could lead to the directory of another user.
+    let metadata = fs::symlink_metadata(&dir)?;
+    if !metadata.is_dir() || metadata.uid() != uid || metadata.permissions().mode() & 0o077 != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} is not a private directory of the user", dir.display()),
+        ));
+    }
+    Ok(dir)
+}
+
+#[cfg(test)]
+#[cfg(unix)]
+mod tests {
+    #[test]
+    fn keeps_the_temporary_directory_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = super::private_temp_dir().unwrap();
+        assert_eq!(super::private_temp_dir().unwrap(), dir);
+        let mode = std::fs::metadata(&dir).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+    }
+}
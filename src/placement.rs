@@ -0,0 +1,481 @@
+//! Where the picker window is placed on the monitors. The positions are in
+//! physical pixels, in the coordinates of the desktop spanning all the
+//! monitors, whose origin is the top left corner of the primary one.
+
+use serde::Deserialize;
+
+/// Where the window opens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Placement {
+    /// Below the caret, or above it if there is no room below.
+    BelowCaret,
+    /// Above the caret, or below it if there is no room above.
+    AboveCaret,
+    /// Below the focused control, or above it if there is no room below.
+    /// Browsers and games often have no caret, but a focused control.
+    FocusedControl,
+    /// At the mouse pointer, like a context menu.
+    MousePointer,
+    /// At the center of the monitor of the caret, else of the focused control,
+    /// else of the mouse pointer.
+    ScreenCenter,
+    /// Where the window was last.
+    LastPosition,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub left: i32,
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Monitor {
+    /// The part of the monitor left to the windows by the taskbars and docks.
+    pub work_area: Rect,
+    /// The physical pixels per logical pixel, e.g. 1.5 for 144 DPI.
+    pub scale_factor: f32,
+}
+
+/// What the window can be placed relative to, when it is known.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Anchors {
+    pub caret: Option<Rect>,
+    pub focused_control: Option<Rect>,
+    pub mouse_pointer: Option<Point>,
+    pub last_position: Option<Point>,
+}
+
+impl Rect {
+    pub fn new(x: i32, y: i32, width: i32, height: i32) -> Self {
+        Self {
+            left: x,
+            top: y,
+            right: x + width,
+            bottom: y + height,
+        }
+    }
+
+    /// Returns the squared distance from the point to the rectangle, 0 inside it.
+    fn distance(&self, point: Point) -> i64 {
+        let dx = (self.left - point.x).max(point.x - self.right).max(0) as i64;
+        let dy = (self.top - point.y).max(point.y - self.bottom).max(0) as i64;
+        dx * dx + dy * dy
+    }
+
+    fn top_left(&self) -> Point {
+        Point {
+            x: self.left,
+            y: self.top,
+        }
+    }
+
+    fn center(&self) -> Point {
+        Point {
+            x: self.left + (self.right - self.left) / 2,
+            y: self.top + (self.bottom - self.top) / 2,
+        }
+    }
+}
+
+/// Returns the position of the top left corner of the window, of the given
+/// logical size, placed as asked and kept in the work area of a monitor.
+/// Returns None if what the window is placed relative to is unknown.
+/// Without any monitor, the window is placed at the scale 1 and not kept anywhere.
+pub fn place(
+    placement: Placement,
+    anchors: &Anchors,
+    window_size: (f32, f32),
+    monitors: &[Monitor],
+) -> Option<Point> {
+    match placement {
+        Placement::BelowCaret => anchors
+            .caret
+            .map(|caret| beside(caret, true, window_size, monitors)),
+        Placement::AboveCaret => anchors
+            .caret
+            .map(|caret| beside(caret, false, window_size, monitors)),
+        Placement::FocusedControl => anchors
+            .focused_control
+            .map(|control| beside(control, true, window_size, monitors)),
+        Placement::MousePointer => anchors
+            .mouse_pointer
+            .map(|pointer| at_pointer(pointer, window_size, monitors)),
+        Placement::ScreenCenter => centered(anchors, window_size, monitors),
+        Placement::LastPosition => anchors.last_position.map(|position| {
+            let monitor = nearest(monitors, position);
+            keep_in(monitor, position, size_on(monitor, window_size))
+        }),
+    }
+}
+
+/// Returns the position of the first placement whose anchor is known.
+pub fn place_first(
+    placements: &[Placement],
+    anchors: &Anchors,
+    window_size: (f32, f32),
+    monitors: &[Monitor],
+) -> Option<Point> {
+    placements
+        .iter()
+        .find_map(|placement| place(*placement, anchors, window_size, monitors))
+}
+
+/// Places the window on a side of the rectangle (the caret or a control),
+/// aligned with its left edge, or on the other side if it only fits there.
+fn beside(rect: Rect, below: bool, window_size: (f32, f32), monitors: &[Monitor]) -> Point {
+    let monitor = nearest(monitors, rect.top_left());
+    let (width, height) = size_on(monitor, window_size);
+    let below_position = rect.bottom;
+    let above_position = rect.top - height;
+    let mut position = Point {
+        x: rect.left,
+        y: if below {
+            below_position
+        } else {
+            above_position
+        },
+    };
+    if let Some(monitor) = monitor {
+        let fits_below = below_position + height <= monitor.work_area.bottom;
+        let fits_above = above_position >= monitor.work_area.top;
+        if below && !fits_below && fits_above {
+            position.y = above_position;
+        } else if !below && !fits_above && fits_below {
+            position.y = below_position;
+        }
+    }
+    keep_in(monitor, position, (width, height))
+}
+
+/// Places the window at the bottom right of the pointer, or on its other
+/// sides where there is no room.
+fn at_pointer(pointer: Point, window_size: (f32, f32), monitors: &[Monitor]) -> Point {
+    let monitor = nearest(monitors, pointer);
+    let (width, height) = size_on(monitor, window_size);
+    let mut position = pointer;
+    if let Some(monitor) = monitor {
+        if pointer.x + width > monitor.work_area.right {
+            position.x = pointer.x - width;
+        }
+        if pointer.y + height > monitor.work_area.bottom {
+            position.y = pointer.y - height;
+        }
+    }
+    keep_in(monitor, position, (width, height))
+}
+
+/// Centers the window in the monitor of the caret, else of the focused
+/// control, else of the mouse pointer, else in the first monitor.
+fn centered(anchors: &Anchors, window_size: (f32, f32), monitors: &[Monitor]) -> Option<Point> {
+    let anchor = anchors
+        .caret
+        .or(anchors.focused_control)
+        .map(|rect| rect.top_left())
+        .or(anchors.mouse_pointer);
+    let monitor = match anchor {
+        Some(anchor) => nearest(monitors, anchor),
+        None => monitors.first(),
+    }?;
+    let (width, height) = size_on(Some(monitor), window_size);
+    let center = monitor.work_area.center();
+    let position = Point {
+        x: center.x - width / 2,
+        y: center.y - height / 2,
+    };
+    Some(keep_in(Some(monitor), position, (width, height)))
+}
+
+/// Returns the monitor of the point, or the nearest one.
+fn nearest(monitors: &[Monitor], point: Point) -> Option<&Monitor> {
+    monitors
+        .iter()
+        .min_by_key(|monitor| monitor.work_area.distance(point))
+}
+
+/// Returns the physical size of a window of the given logical size, on the monitor.
+fn size_on(monitor: Option<&Monitor>, (width, height): (f32, f32)) -> (i32, i32) {
+    let scale_factor = monitor.map_or(1., |monitor| monitor.scale_factor);
+    (
+        (width * scale_factor).round() as i32,
+        (height * scale_factor).round() as i32,
+    )
+}
+
+/// Moves the window into the work area, its top left corner first if the
+/// window is larger than the area.
+fn keep_in(monitor: Option<&Monitor>, position: Point, (width, height): (i32, i32)) -> Point {
+    let Some(monitor) = monitor else {
+        return position;
+    };
+    let work_area = monitor.work_area;
+    Point {
+        x: position.x.min(work_area.right - width).max(work_area.left),
+        y: position.y.min(work_area.bottom - height).max(work_area.top),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 1920x1040 monitor at the origin, with a taskbar below it.
+    const WORK_AREA: Rect = Rect {
+        left: 0,
+        top: 0,
+        right: 1920,
+        bottom: 1040,
+    };
+    const WINDOW_SIZE: (f32, f32) = (400., 300.);
+
+    fn monitor(work_area: Rect, scale_factor: f32) -> Monitor {
+        Monitor {
+            work_area,
+            scale_factor,
+        }
+    }
+
+    fn caret_at(x: i32, y: i32) -> Anchors {
+        Anchors {
+            caret: Some(Rect::new(x, y, 2, 20)),
+            ..Anchors::default()
+        }
+    }
+
+    fn place_in(placement: Placement, anchors: &Anchors, monitors: &[Monitor]) -> Point {
+        place(placement, anchors, WINDOW_SIZE, monitors).unwrap()
+    }
+
+    #[test]
+    fn keeps_the_window_in_the_work_area() {
+        let monitor = monitor(WORK_AREA, 1.);
+        let cases = [
+            (Point { x: 1800, y: 100 }, Point { x: 1520, y: 100 }),
+            (Point { x: 100, y: 900 }, Point { x: 100, y: 740 }),
+            (Point { x: -50, y: -10 }, Point { x: 0, y: 0 }),
+            (Point { x: 2000, y: 2000 }, Point { x: 1520, y: 740 }),
+            (Point { x: 500, y: 400 }, Point { x: 500, y: 400 }),
+        ];
+        for (position, kept) in cases {
+            assert_eq!(keep_in(Some(&monitor), position, (400, 300)), kept);
+        }
+    }
+
+    #[test]
+    fn flips_above_the_caret_at_the_bottom() {
+        let monitors = [monitor(WORK_AREA, 1.)];
+        let below = place_in(Placement::BelowCaret, &caret_at(100, 500), &monitors);
+        assert_eq!(below, Point { x: 100, y: 520 });
+
+        // The caret is 20 pixels high: 740 + 300 fits, 741 + 300 does not.
+        let below = place_in(Placement::BelowCaret, &caret_at(100, 720), &monitors);
+        assert_eq!(below, Point { x: 100, y: 740 });
+        let above = place_in(Placement::BelowCaret, &caret_at(100, 721), &monitors);
+        assert_eq!(above, Point { x: 100, y: 421 });
+    }
+
+    #[test]
+    fn flips_below_the_caret_at_the_top() {
+        let monitors = [monitor(WORK_AREA, 1.)];
+        let above = place_in(Placement::AboveCaret, &caret_at(100, 300), &monitors);
+        assert_eq!(above, Point { x: 100, y: 0 });
+        let below = place_in(Placement::AboveCaret, &caret_at(100, 299), &monitors);
+        assert_eq!(below, Point { x: 100, y: 319 });
+    }
+
+    #[test]
+    fn stays_on_the_asked_side_when_no_side_fits() {
+        // The window is as high as the work area, less the caret.
+        let short = Rect::new(0, 0, 1920, 600);
+        let monitors = [monitor(short, 1.)];
+        let below = place_in(Placement::BelowCaret, &caret_at(100, 290), &monitors);
+        assert_eq!(below, Point { x: 100, y: 300 });
+        let above = place_in(Placement::AboveCaret, &caret_at(100, 290), &monitors);
+        assert_eq!(above, Point { x: 100, y: 0 });
+    }
+
+    /// The primary monitor, one at its left at the scale 1.5, and one above it at the scale 2.
+    fn monitors() -> [Monitor; 3] {
+        [
+            monitor(WORK_AREA, 1.),
+            monitor(Rect::new(-2880, 0, 2880, 1620), 1.5),
+            monitor(Rect::new(0, -2160, 3840, 2160), 2.),
+        ]
+    }
+
+    #[test]
+    fn places_relative_to_each_anchor() {
+        let monitors = [monitor(WORK_AREA, 1.)];
+        let anchors = Anchors {
+            caret: Some(Rect::new(100, 400, 2, 20)),
+            focused_control: Some(Rect::new(50, 400, 300, 30)),
+            mouse_pointer: Some(Point { x: 800, y: 500 }),
+            last_position: Some(Point { x: 1000, y: 100 }),
+        };
+        let cases = [
+            (Placement::BelowCaret, Point { x: 100, y: 420 }),
+            (Placement::AboveCaret, Point { x: 100, y: 100 }),
+            (Placement::FocusedControl, Point { x: 50, y: 430 }),
+            (Placement::MousePointer, Point { x: 800, y: 500 }),
+            (Placement::ScreenCenter, Point { x: 760, y: 370 }),
+            (Placement::LastPosition, Point { x: 1000, y: 100 }),
+        ];
+        for (placement, position) in cases {
+            assert_eq!(
+                place_in(placement, &anchors, &monitors),
+                position,
+                "{placement:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn needs_the_anchor_of_the_placement() {
+        let monitors = [monitor(WORK_AREA, 1.)];
+        for placement in [
+            Placement::BelowCaret,
+            Placement::AboveCaret,
+            Placement::FocusedControl,
+            Placement::MousePointer,
+            Placement::LastPosition,
+        ] {
+            let position = place(placement, &Anchors::default(), WINDOW_SIZE, &monitors);
+            assert_eq!(position, None, "{placement:?}");
+        }
+        // The center of the first monitor, without any anchor.
+        let center = place(
+            Placement::ScreenCenter,
+            &Anchors::default(),
+            WINDOW_SIZE,
+            &monitors,
+        );
+        assert_eq!(center, Some(Point { x: 760, y: 370 }));
+        let center = place(
+            Placement::ScreenCenter,
+            &Anchors::default(),
+            WINDOW_SIZE,
+            &[],
+        );
+        assert_eq!(center, None);
+
+        let placements = [Placement::BelowCaret, Placement::MousePointer];
+        let anchors = Anchors {
+            mouse_pointer: Some(Point { x: 10, y: 20 }),
+            ..Anchors::default()
+        };
+        let first = place_first(&placements, &anchors, WINDOW_SIZE, &monitors);
+        assert_eq!(first, Some(Point { x: 10, y: 20 }));
+        let first = place_first(&placements, &caret_at(10, 20), WINDOW_SIZE, &monitors);
+        assert_eq!(first, Some(Point { x: 10, y: 40 }));
+    }
+
+    #[test]
+    fn flips_the_focused_control_above_at_the_bottom() {
+        let monitors = [monitor(WORK_AREA, 1.)];
+        let anchors = Anchors {
+            focused_control: Some(Rect::new(50, 900, 300, 30)),
+            ..Anchors::default()
+        };
+        let above = place_in(Placement::FocusedControl, &anchors, &monitors);
+        assert_eq!(above, Point { x: 50, y: 600 });
+    }
+
+    #[test]
+    fn flips_around_the_pointer_at_the_right_and_the_bottom() {
+        let monitors = [monitor(WORK_AREA, 1.)];
+        let at = |x, y| {
+            let anchors = Anchors {
+                mouse_pointer: Some(Point { x, y }),
+                ..Anchors::default()
+            };
+            place_in(Placement::MousePointer, &anchors, &monitors)
+        };
+        assert_eq!(at(1520, 740), Point { x: 1520, y: 740 });
+        assert_eq!(at(1521, 100), Point { x: 1121, y: 100 });
+        assert_eq!(at(100, 741), Point { x: 100, y: 441 });
+        assert_eq!(at(1900, 1000), Point { x: 1500, y: 700 });
+    }
+
+    #[test]
+    fn keeps_the_caret_placement_at_the_left_and_the_right() {
+        let monitors = [monitor(WORK_AREA, 1.)];
+        let left = place_in(Placement::BelowCaret, &caret_at(-30, 100), &monitors);
+        assert_eq!(left, Point { x: 0, y: 120 });
+        let right = place_in(Placement::BelowCaret, &caret_at(1700, 100), &monitors);
+        assert_eq!(right, Point { x: 1520, y: 120 });
+    }
+
+    #[test]
+    fn places_on_the_monitors_with_negative_positions() {
+        let monitors = monitors();
+        // On the left monitor, the window is 600x450 and flips above the caret.
+        let position = place_in(Placement::BelowCaret, &caret_at(-100, 1500), &monitors);
+        assert_eq!(position, Point { x: -600, y: 1050 });
+        // On the top monitor, the window is 800x600 and flips below the caret.
+        let position = place_in(Placement::AboveCaret, &caret_at(3000, -2150), &monitors);
+        assert_eq!(position, Point { x: 3000, y: -2130 });
+
+        let anchors = Anchors {
+            mouse_pointer: Some(Point { x: -10, y: 1610 }),
+            ..Anchors::default()
+        };
+        let position = place_in(Placement::MousePointer, &anchors, &monitors);
+        assert_eq!(position, Point { x: -610, y: 1160 });
+        let position = place_in(Placement::ScreenCenter, &anchors, &monitors);
+        assert_eq!(position, Point { x: -1740, y: 585 });
+
+        let anchors = Anchors {
+            last_position: Some(Point { x: -50, y: -100 }),
+            ..Anchors::default()
+        };
+        // The nearest monitor is the top one.
+        let position = place_in(Placement::LastPosition, &anchors, &monitors);
+        assert_eq!(position, Point { x: 0, y: -600 });
+    }
+
+    #[test]
+    fn chooses_the_monitor_of_the_point_or_the_nearest_one() {
+        let monitors = monitors();
+        let nearest_idx = |x, y| {
+            let monitor = nearest(&monitors, Point { x, y }).unwrap();
+            monitors.iter().position(|m| m == monitor).unwrap()
+        };
+        assert_eq!(nearest_idx(100, 100), 0);
+        assert_eq!(nearest_idx(-100, 100), 1);
+        assert_eq!(nearest_idx(100, -100), 2);
+        // Below the taskbar of the primary monitor.
+        assert_eq!(nearest_idx(100, 1070), 0);
+        // Below the left monitor, nearer to it than to the primary one.
+        assert_eq!(nearest_idx(-10, 1700), 1);
+        // At the right of the top monitor.
+        assert_eq!(nearest_idx(5000, -50), 2);
+        assert_eq!(nearest(&[], Point { x: 0, y: 0 }), None);
+    }
+
+    #[test]
+    fn scales_the_window_size() {
+        let size = |scale_factor| size_on(Some(&monitor(WORK_AREA, scale_factor)), (401., 301.));
+        assert_eq!(size(1.), (401, 301));
+        assert_eq!(size(1.5), (602, 452));
+        assert_eq!(size(2.), (802, 602));
+        assert_eq!(size_on(None, (401., 301.)), (401, 301));
+    }
+
+    #[test]
+    fn keeps_the_top_left_corner_of_a_larger_window() {
+        let monitor = monitor(Rect::new(-1280, 0, 1280, 1024), 1.);
+        let kept = keep_in(Some(&monitor), Point { x: -1000, y: 200 }, (1600, 1200));
+        assert_eq!(kept, Point { x: -1280, y: 0 });
+        let kept = keep_in(None, Point { x: -1000, y: 200 }, (1600, 1200));
+        assert_eq!(kept, Point { x: -1000, y: 200 });
+    }
+}
@@ -1,7 +1,9 @@
-use core::time;
-use std::sync::{
-    atomic::{AtomicBool, Ordering},
-    Arc,
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
 };
 
 pub struct Poller {
@@ -9,7 +11,17 @@ pub struct Poller {
 }
 
 impl Poller {
-    pub fn new(mut f: impl FnMut() + Send + 'static) -> Self {
+    /// Calls `f` every `interval` until the poller is stopped.
+    pub fn new(interval: Duration, f: impl FnMut() + Send + 'static) -> Self {
+        Self::with_interval(move || interval, f)
+    }
+
+    /// Calls `f` until the poller is stopped, waiting the interval returned
+    /// by `interval` between two calls, so that it can change.
+    pub fn with_interval(
+        interval: impl Fn() -> Duration + Send + 'static,
+        mut f: impl FnMut() + Send + 'static,
+    ) -> Self {
         let arc = Arc::new(AtomicBool::new(false));
         let arc_cloned = arc.clone();
         // This is not nice. Might switch to slint::spawn
@@ -18,7 +30,7 @@ impl Poller {
                 break;
             }
             f();
-            std::thread::sleep(time::Duration::from_millis(100));
+            std::thread::sleep(interval());
         });
 
         Poller { semaphore: arc }
@@ -0,0 +1,218 @@
+//! The rules of the settings, choosing how the emoji is output depending
+//! on the window it goes to.
+
+use serde::Deserialize;
+
+use crate::settings::{Insertion, OutputMode, Settings};
+use crate::window_info::WindowInfo;
+
+/// Overrides how the emoji is output into the windows it matches.
+/// The process and the class are compared ignoring the case.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Rule {
+    /// The file name of the process owning the window (e.g. "mstsc.exe").
+    pub process: Option<String>,
+    /// The class of the window.
+    pub class: Option<String>,
+    pub output: Option<OutputMode>,
+    pub insertion: Option<Insertion>,
+    pub format: Option<EmojiFormat>,
+}
+
+/// What is output for an emoji.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmojiFormat {
+    /// The emoji itself.
+    #[default]
+    Emoji,
+    /// Its shortcode (e.g. `:thumbsup:`), for the applications replacing them.
+    /// Emojis without a shortcode are output as they are.
+    Shortcode,
+}
+
+/// How the emoji is output into a window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Output {
+    pub mode: OutputMode,
+    pub insertion: Insertion,
+    pub format: EmojiFormat,
+}
+
+impl Rule {
+    /// Whether the rule applies to the window. A rule matching neither
+    /// a process nor a class is rejected by the settings.
+    pub fn matches(&self, window: &WindowInfo) -> bool {
+        let matches = |expected: &Option<String>, actual: &Option<String>| match expected {
+            None => true,
+            Some(expected) => actual
+                .as_ref()
+                .is_some_and(|actual| actual.eq_ignore_ascii_case(expected)),
+        };
+        matches(&self.process, &window.process) && matches(&self.class, &window.class)
+    }
+}
+
+/// Returns how the emoji is output into the window: as set by the first
+/// rule matching it, else as set by the settings.
+pub fn output_for(settings: &Settings, window: Option<&WindowInfo>) -> Output {
+    let rule = window.and_then(|window| settings.rules.iter().find(|rule| rule.matches(window)));
+    let rule = rule.cloned().unwrap_or_default();
+    Output {
+        mode: rule.output.unwrap_or(settings.output),
+        insertion: rule.insertion.unwrap_or(settings.insertion),
+        format: rule.format.unwrap_or_default(),
+    }
+}
+
+impl EmojiFormat {
+    pub fn apply(self, emoji: &str) -> String {
+        match self {
+            EmojiFormat::Emoji => emoji.to_owned(),
+            EmojiFormat::Shortcode => shortcode(emoji).unwrap_or_else(|| emoji.to_owned()),
+        }
+    }
+}
+
+/// Returns the shortcode of the emoji. The skin tones have no shortcode of
+/// their own: the one of the default tone is followed by the tone, as in Slack.
+fn shortcode(emoji: &str) -> Option<String> {
+    let emoji = emojis::get(emoji)?;
+    if let Some(shortcode) = emoji.shortcode() {
+        return Some(format!(":{}:", shortcode));
+    }
+    let tone = match emoji.skin_tone()? {
+        emojis::SkinTone::Light => 2,
+        emojis::SkinTone::MediumLight => 3,
+        emojis::SkinTone::Medium => 4,
+        emojis::SkinTone::MediumDark => 5,
+        emojis::SkinTone::Dark => 6,
+        _ => return None,
+    };
+    let default = emoji.with_skin_tone(emojis::SkinTone::Default)?;
+    Some(format!(":{}::skin-tone-{}:", default.shortcode()?, tone))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::window_info::{FakeWindows, WindowInfoProvider};
+
+    fn info(process: Option<&str>, class: Option<&str>) -> WindowInfo {
+        WindowInfo {
+            id: 1,
+            process: process.map(str::to_owned),
+            class: class.map(str::to_owned),
+        }
+    }
+
+    fn window(process: Option<&str>, class: Option<&str>) -> FakeWindows {
+        FakeWindows(Some(info(process, class)))
+    }
+
+    fn output_in(settings: &Settings, windows: &impl WindowInfoProvider) -> Output {
+        output_for(settings, windows.foreground().as_ref())
+    }
+
+    fn settings(content: &str) -> Settings {
+        Settings::parse(content).unwrap()
+    }
+
+    #[test]
+    fn matches_the_process_or_the_class_ignoring_the_case() {
+        let process = Rule {
+            process: Some("MSTSC.exe".to_owned()),
+            ..Rule::default()
+        };
+        assert!(process.matches(&info(Some("mstsc.EXE"), None)));
+        assert!(!process.matches(&info(Some("notepad.exe"), None)));
+        assert!(!process.matches(&info(None, Some("mstsc.exe"))));
+
+        let class = Rule {
+            class: Some("consolewindowclass".to_owned()),
+            ..Rule::default()
+        };
+        assert!(class.matches(&info(Some("cmd.exe"), Some("ConsoleWindowClass"))));
+        assert!(!class.matches(&info(Some("consolewindowclass"), None)));
+    }
+
+    #[test]
+    fn matches_both_the_process_and_the_class() {
+        let rule = Rule {
+            process: Some("code".to_owned()),
+            class: Some("Code".to_owned()),
+            ..Rule::default()
+        };
+        assert!(rule.matches(&info(Some("code"), Some("code"))));
+        assert!(!rule.matches(&info(Some("code"), Some("Terminal"))));
+        assert!(!rule.matches(&info(Some("slack"), Some("Code"))));
+        assert!(!rule.matches(&info(Some("code"), None)));
+    }
+
+    #[test]
+    fn the_first_matching_rule_applies() {
+        let settings = settings(
+            r#"
+            output = "type"
+
+            [[rules]]
+            process = "slack"
+            format = "shortcode"
+
+            [[rules]]
+            process = "slack"
+            output = "clipboard"
+
+            [[rules]]
+            class = "Slack"
+            insertion = "paste"
+            "#,
+        );
+        let output = output_in(&settings, &window(Some("Slack"), Some("Slack")));
+        assert_eq!(
+            output,
+            Output {
+                mode: OutputMode::Type,
+                insertion: Insertion::Type,
+                format: EmojiFormat::Shortcode,
+            }
+        );
+        let output = output_in(&settings, &window(Some("firefox"), Some("slack")));
+        assert_eq!(output.insertion, Insertion::Paste);
+        assert_eq!(output.format, EmojiFormat::Emoji);
+    }
+
+    #[test]
+    fn uses_the_settings_without_a_foreground_window() {
+        let settings = settings(
+            r#"
+            output = "type-or-clipboard"
+            insertion = "paste"
+
+            [[rules]]
+            process = "mstsc.exe"
+            output = "clipboard"
+            "#,
+        );
+        let expected = Output {
+            mode: OutputMode::TypeOrClipboard,
+            insertion: Insertion::Paste,
+            format: EmojiFormat::Emoji,
+        };
+        assert_eq!(output_in(&settings, &FakeWindows(None)), expected);
+        assert_eq!(output_in(&settings, &window(None, None)), expected);
+    }
+
+    #[test]
+    fn outputs_the_shortcodes_with_the_skin_tones() {
+        let shortcode = EmojiFormat::Shortcode;
+        assert_eq!(shortcode.apply("👍"), ":+1:");
+        assert_eq!(shortcode.apply("👍🏻"), ":+1::skin-tone-2:");
+        assert_eq!(shortcode.apply("👍🏿"), ":+1::skin-tone-6:");
+        assert_eq!(shortcode.apply("👋🏽"), ":wave::skin-tone-4:");
+        // Not an emoji.
+        assert_eq!(shortcode.apply("abc"), "abc");
+        assert_eq!(EmojiFormat::Emoji.apply("👍🏻"), "👍🏻");
+    }
+}
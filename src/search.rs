@@ -0,0 +1,375 @@
+use std::collections::HashMap;
+
+use crate::emoji::EmojiWrapper;
+
+/// Weight of the frecency of an emoji against its match score.
+/// An emoji used a lot can outrank a better, but never used, match.
+const FRECENCY_WEIGHT: f64 = 20.0;
+
+/// Score lost for every typo of a fuzzy match.
+const TYPO_PENALTY: f64 = 5.0;
+
+/// Queries shorter than this are only matched literally.
+const FUZZY_MIN_LEN: usize = 3;
+
+/// How well an emoji matches a query, from the worst to the best.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MatchQuality {
+    /// The letters of the query appear, in order, in a searched text.
+    Subsequence,
+    /// Every word of the query is close to a word of the searched texts.
+    Typo,
+    /// The query is somewhere in a searched text.
+    Substring,
+    /// A word of a searched text starts with the query.
+    WordStart,
+    /// A searched text starts with the query.
+    Prefix,
+    /// The query is a shortcode.
+    ExactShortcode,
+}
+
+impl MatchQuality {
+    fn score(&self) -> f64 {
+        match self {
+            MatchQuality::Subsequence => 5.0,
+            MatchQuality::Typo => 20.0,
+            MatchQuality::Substring => 25.0,
+            MatchQuality::WordStart => 50.0,
+            MatchQuality::Prefix => 75.0,
+            MatchQuality::ExactShortcode => 100.0,
+        }
+    }
+}
+
+/// The result of matching an emoji against a query.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Match {
+    pub quality: MatchQuality,
+    /// How many edits were needed to match, for typo-tolerant matches.
+    pub typos: usize,
+}
+
+impl Match {
+    fn exact(quality: MatchQuality) -> Self {
+        Self { quality, typos: 0 }
+    }
+
+    pub fn score(&self) -> f64 {
+        self.quality.score() - TYPO_PENALTY * self.typos as f64
+    }
+}
+
+/// A normalized search query.
+pub struct Query {
+    text: String,
+    words: Vec<String>,
+}
+
+impl Query {
+    pub fn new(query: &str) -> Self {
+        let text = normalize(query.trim());
+        let words = words(&text).map(String::from).collect();
+        Self { text, words }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// Returns how well the emoji matches the query, if it does.
+    /// Literal matches are always preferred over typo-tolerant ones.
+    pub fn matches(&self, emoji: EmojiWrapper) -> Option<Match> {
+        if self.is_empty() {
+            return None;
+        }
+        if emoji.shortcodes().any(|s| s == self.text) {
+            return Some(Match::exact(MatchQuality::ExactShortcode));
+        }
+        let fields = fields(emoji);
+        let literal = fields
+            .iter()
+            .filter_map(|field| literal_quality(field, &self.text))
+            .max();
+        if let Some(quality) = literal {
+            return Some(Match::exact(quality));
+        }
+
+        if self.text.chars().count() < FUZZY_MIN_LEN {
+            return None;
+        }
+        self.typo_match(&fields).or_else(|| {
+            fields
+                .iter()
+                .any(|field| is_subsequence(&self.text, field))
+                .then(|| Match::exact(MatchQuality::Subsequence))
+        })
+    }
+
+    /// Matches every word of the query against the closest word of the fields.
+    fn typo_match(&self, fields: &[String]) -> Option<Match> {
+        let mut typos = 0;
+        for query_word in self.words.iter() {
+            let max = max_typos(query_word);
+            typos += fields
+                .iter()
+                .flat_map(|field| words(field))
+                .filter_map(|word| prefix_distance(query_word, word, max))
+                .min()?;
+        }
+        Some(Match {
+            quality: MatchQuality::Typo,
+            typos,
+        })
+    }
+}
+
+/// Returns the normalized texts of an emoji that can be searched: its English
+/// name, its shortcodes and its CLDR name and keywords in the current language.
+fn fields(emoji: EmojiWrapper) -> Vec<String> {
+    let annotations = emoji.tts_name().into_iter().chain(emoji.keywords());
+    std::iter::once(emoji.name())
+        .chain(emoji.shortcodes())
+        .map(normalize)
+        .chain(annotations.map(normalize))
+        .collect()
+}
+
+/// Lowercases the text and removes the most common diacritics,
+/// so that "etoile" finds "étoile".
+fn normalize(text: &str) -> String {
+    let mut normalized = String::with_capacity(text.len());
+    for c in text.to_lowercase().chars() {
+        match c {
+            'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' => normalized.push('a'),
+            'ç' => normalized.push('c'),
+            'è' | 'é' | 'ê' | 'ë' => normalized.push('e'),
+            'ì' | 'í' | 'î' | 'ï' => normalized.push('i'),
+            'ñ' => normalized.push('n'),
+            'ò' | 'ó' | 'ô' | 'õ' | 'ö' => normalized.push('o'),
+            'ù' | 'ú' | 'û' | 'ü' => normalized.push('u'),
+            'ý' | 'ÿ' => normalized.push('y'),
+            'æ' => normalized.push_str("ae"),
+            'œ' => normalized.push_str("oe"),
+            'ß' => normalized.push_str("ss"),
+            c => normalized.push(c),
+        }
+    }
+    normalized
+}
+
+/// Splits a text into its alphanumeric words.
+fn words(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+}
+
+/// Returns how well a field literally contains the query, if it does.
+fn literal_quality(field: &str, query: &str) -> Option<MatchQuality> {
+    field
+        .match_indices(query)
+        .map(|(idx, _)| {
+            if idx == 0 {
+                MatchQuality::Prefix
+            } else if field[..idx].ends_with(|c: char| !c.is_alphanumeric()) {
+                MatchQuality::WordStart
+            } else {
+                MatchQuality::Substring
+            }
+        })
+        .max()
+}
+
+/// How many typos are tolerated in a word of the query.
+fn max_typos(word: &str) -> usize {
+    match word.chars().count() {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    }
+}
+
+/// Returns whether the letters of `query` (ignoring spaces) appear in order
+/// in `text`, the first letter starting it (e.g. "thmbup" in "thumbsup".)
+fn is_subsequence(query: &str, text: &str) -> bool {
+    let mut query = query.chars().filter(|c| !c.is_whitespace());
+    let mut text = text.chars();
+    match (query.next(), text.next()) {
+        (Some(q), Some(t)) if q == t => query.all(|q| text.any(|c| c == q)),
+        _ => false,
+    }
+}
+
+/// Computes the last row of the optimal string alignment distance matrix
+/// (the edit distance where swapping two adjacent letters counts as one edit)
+/// between `a` and every prefix of `b`. Returns `None` as soon as every
+/// distance is known to exceed `max`.
+fn distance_row(a: &str, b: &str, max: usize) -> Option<Vec<usize>> {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    let mut before: Vec<usize> = vec![0; b.len() + 1];
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current: Vec<usize> = vec![0; b.len() + 1];
+    for i in 1..=a.len() {
+        current[0] = i;
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut d = (previous[j] + 1)
+                .min(current[j - 1] + 1)
+                .min(previous[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d = d.min(before[j - 2] + 1);
+            }
+            current[j] = d;
+        }
+        if current.iter().all(|d| *d > max) {
+            return None;
+        }
+        std::mem::swap(&mut before, &mut previous);
+        std::mem::swap(&mut previous, &mut current);
+    }
+    Some(previous)
+}
+
+/// Returns the smallest edit distance between `a` and a prefix of `b`,
+/// if it is at most `max`. This tolerates typos in incomplete words.
+fn prefix_distance(a: &str, b: &str, max: usize) -> Option<usize> {
+    distance_row(a, b, max)?
+        .into_iter()
+        .min()
+        .filter(|d| *d <= max)
+}
+
+/// Returns the candidates matching the query, the best first. The match
+/// score is mixed with the frecency of the emoji, as given by the history.
+/// Candidates with equal scores are kept in their original order.
+pub fn rank<T>(
+    query: &Query,
+    candidates: impl IntoIterator<Item = (EmojiWrapper, T)>,
+    frecencies: &HashMap<EmojiWrapper, f64>,
+) -> Vec<T> {
+    let mut scored: Vec<(f64, T)> = candidates
+        .into_iter()
+        .filter_map(|(emoji, candidate)| {
+            let score = query.matches(emoji)?.score();
+            let frecency = frecencies.get(&emoji).copied().unwrap_or_default();
+            Some((score + FRECENCY_WEIGHT * frecency.ln_1p(), candidate))
+        })
+        .collect();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+    scored.into_iter().map(|(_, candidate)| candidate).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn emoji(code: &str) -> EmojiWrapper {
+        EmojiWrapper(emojis::get(code).unwrap())
+    }
+
+    fn quality(query: &str, code: &str) -> Option<MatchQuality> {
+        Query::new(query).matches(emoji(code)).map(|m| m.quality)
+    }
+
+    #[test]
+    fn distance_counts_edits() {
+        assert_eq!(prefix_distance("kitten", "kitten", 2), Some(0));
+        assert_eq!(prefix_distance("kitten", "sitten", 2), Some(1));
+        assert_eq!(prefix_distance("kiten", "kitten", 2), Some(1));
+        assert_eq!(prefix_distance("kittten", "kitten", 2), Some(1));
+        assert_eq!(prefix_distance("kitten", "sitting", 3), Some(2));
+    }
+
+    #[test]
+    fn distance_counts_a_transposition_as_one_edit() {
+        assert_eq!(prefix_distance("teh", "the", 1), Some(1));
+        assert_eq!(prefix_distance("hte", "the", 1), Some(1));
+        assert_eq!(prefix_distance("thubms", "thumbs", 1), Some(1));
+    }
+
+    #[test]
+    fn distance_is_to_the_closest_prefix() {
+        assert_eq!(prefix_distance("thumb", "thumbsup", 0), Some(0));
+        assert_eq!(prefix_distance("tumb", "thumbsup", 1), Some(1));
+        assert_eq!(prefix_distance("", "thumbsup", 0), Some(0));
+        assert_eq!(prefix_distance("thumbsup", "thumb", 2), None);
+    }
+
+    #[test]
+    fn distance_aborts_above_the_threshold() {
+        assert_eq!(distance_row("abcdef", "uvwxyz", 1), None);
+        assert_eq!(prefix_distance("star", "stop", 1), None);
+        assert_eq!(prefix_distance("star", "stop", 2), Some(2));
+        // The row is kept as long as one of its distances is within the threshold.
+        assert_eq!(distance_row("ab", "abc", 0), Some(vec![2, 1, 0, 1]));
+    }
+
+    #[test]
+    fn subsequence_starts_with_the_first_letter() {
+        assert!(is_subsequence("thmbup", "thumbsup"));
+        assert!(is_subsequence("th up", "thumbsup"));
+        assert!(is_subsequence("thumbsup", "thumbsup"));
+        assert!(!is_subsequence("humbsup", "thumbsup"));
+        assert!(!is_subsequence("thupm", "thumbsup"));
+        assert!(!is_subsequence("", "thumbsup"));
+        assert!(!is_subsequence("t", ""));
+    }
+
+    #[test]
+    fn literal_matches_are_graded() {
+        assert_eq!(
+            quality("thumbsup", "👍"),
+            Some(MatchQuality::ExactShortcode)
+        );
+        assert_eq!(quality("Thumb", "👍"), Some(MatchQuality::Prefix));
+        assert_eq!(quality("smiling", "🙂"), Some(MatchQuality::WordStart));
+        assert_eq!(quality("umbs", "👍"), Some(MatchQuality::Substring));
+    }
+
+    #[test]
+    fn fuzzy_matches_are_graded() {
+        let typo = Query::new("thubms").matches(emoji("👍")).unwrap();
+        assert_eq!(typo.quality, MatchQuality::Typo);
+        assert_eq!(typo.typos, 1);
+        assert_eq!(quality("thmbup", "👍"), Some(MatchQuality::Subsequence));
+        assert_eq!(quality("xylophone", "👍"), None);
+    }
+
+    #[test]
+    fn short_queries_are_only_matched_literally() {
+        assert_eq!(quality("tu", "👍"), None);
+        assert_eq!(quality("th", "👍"), Some(MatchQuality::Prefix));
+        assert_eq!(quality("  ", "👍"), None);
+    }
+
+    #[test]
+    fn rank_puts_the_best_match_first() {
+        let candidates = [(emoji("👎"), "down"), (emoji("👍"), "up")];
+        let ranked = rank(&Query::new("thumbsup"), candidates, &HashMap::new());
+        assert_eq!(ranked, ["up", "down"]);
+    }
+
+    #[test]
+    fn rank_keeps_the_order_of_equal_scores() {
+        let candidates = [(emoji("👎"), "down"), (emoji("👍"), "up")];
+        let ranked = rank(&Query::new("thumbs"), candidates, &HashMap::new());
+        assert_eq!(ranked, ["down", "up"]);
+    }
+
+    #[test]
+    fn rank_favors_the_frequently_used_emojis() {
+        let candidates = [(emoji("👎"), "down"), (emoji("👍"), "up")];
+        let frecencies = HashMap::from([(emoji("👍"), 3.0)]);
+        let ranked = rank(&Query::new("thumbs"), candidates, &frecencies);
+        assert_eq!(ranked, ["up", "down"]);
+    }
+
+    #[test]
+    fn rank_drops_the_emojis_not_matching() {
+        let candidates = [(emoji("👍"), "up"), (emoji("🍕"), "pizza")];
+        let ranked = rank(&Query::new("pizza"), candidates, &HashMap::new());
+        assert_eq!(ranked, ["pizza"]);
+    }
+}
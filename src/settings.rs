@@ -0,0 +1,461 @@
+use std::{
+    fs, io,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+    time::SystemTime,
+};
+
+use serde::{Deserialize, Deserializer};
+
+use crate::placement::Placement;
+use crate::rules::Rule;
+use crate::shortcut::Binding;
+
+/// Name of the file, in the user's config directory, where the settings are stored.
+const SETTINGS_FILE: &str = "config.toml";
+
+/// Bounds of the interval between two checks of the notifiers, in milliseconds.
+const POLL_INTERVAL_RANGE: RangeInclusive<u64> = 10..=1000;
+/// Bounds of the size of the emoji buttons, in logical pixels.
+const BUTTON_SIZE_RANGE: RangeInclusive<f32> = 16.0..=200.0;
+
+/// Set when the settings must be reloaded even if their file did not change.
+static RELOAD_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, thiserror::Error)]
+pub enum SettingsError {
+    #[error("Failed to read the settings. Reason: {0}")]
+    Io(#[from] io::Error),
+    #[error("Failed to parse the settings. Reason: {0}")]
+    Parse(#[from] toml::de::Error),
+    #[error("Invalid value for `{key}` in the settings: {reason}")]
+    Invalid { key: &'static str, reason: String },
+}
+
+/// The user settings. Every field is optional in the file.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    /// Language of the emoji names, keywords and user interface (e.g. "fr").
+    /// Defaults to the system's language.
+    pub locale: Option<String>,
+    /// Shortcuts opening the picker, each one possibly into a given group.
+    pub shortcuts: Vec<Binding>,
+    /// Interval between two checks of the notifiers (shortcut, tray icon…), in milliseconds.
+    pub poll_interval_ms: u64,
+    /// Size of the emoji buttons, in logical pixels.
+    pub button_width: f32,
+    pub button_height: f32,
+    /// Colors of the picker.
+    pub theme: Theme,
+    /// What is done with the selected emoji.
+    pub output: OutputMode,
+    /// File the selected emojis are appended to, with the `file` output.
+    pub output_file: Option<PathBuf>,
+    /// How the emoji is inserted in the focused window, when it is typed.
+    pub insertion: Insertion,
+    /// How the keys are sent to the focused window on Linux.
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    pub typing_backend: TypingBackend,
+    /// Where the window opens: the first placement whose anchor is known applies.
+    /// A single placement can be given instead of a list.
+    #[serde(deserialize_with = "one_or_many")]
+    pub placement: Vec<Placement>,
+    /// Overrides of the output for given windows. The first matching rule applies.
+    pub rules: Vec<Rule>,
+    /// Directory of the emoji images, named as in Twemoji (e.g. `1f600.svg`.)
+    pub image_dir: PathBuf,
+    pub features: Features,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    /// Follows the system's color scheme.
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputMode {
+    /// Types the emoji if the picker was opened by the shortcut, else copies it.
+    #[default]
+    Auto,
+    /// Always types the emoji in the focused window.
+    Type,
+    /// Always copies the emoji to the clipboard.
+    Clipboard,
+    /// Types the emoji, or copies it if it cannot be typed.
+    TypeOrClipboard,
+    /// Appends the emoji to the `output_file`.
+    File,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Insertion {
+    /// Types the emoji as Unicode characters.
+    #[default]
+    Type,
+    /// Copies the emoji, presses Ctrl+V, then restores the clipboard
+    /// (without the formats it cannot copy: see `PasteSink`.)
+    Paste,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TypingBackend {
+    /// The virtual keyboard on Wayland, XTest on X11.
+    #[default]
+    Auto,
+    /// The XTest extension of X11.
+    Xtest,
+    /// The virtual-keyboard protocol of Wayland.
+    VirtualKeyboard,
+    /// A keyboard device made with uinput, typing the code points after Ctrl+Shift+U.
+    /// It presses the keys of the hexadecimal digits on QWERTY: other layouts type others.
+    Uinput,
+}
+
+/// Runtime toggles of the features. A feature must also be compiled in to be enabled.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Features {
+    /// Places the window near the caret.
+    #[cfg_attr(
+        not(all(feature = "caret", any(target_os = "windows", target_os = "linux"))),
+        allow(dead_code)
+    )]
+    pub caret: bool,
+    /// Closes the picker when clicking outside of it (Windows only.)
+    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+    pub back_click: bool,
+    /// Shows a tray icon that opens the picker (Windows only.)
+    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+    pub tray_icon: bool,
+    /// Redirects the keys typed while the picker is open to the filter (Windows only.)
+    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+    pub key_redir: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            locale: None,
+            shortcuts: vec![Binding {
+                keys: "Win+.".parse().unwrap(),
+                group: None,
+            }],
+            poll_interval_ms: 100,
+            button_width: 50.,
+            button_height: 40.,
+            theme: Theme::default(),
+            output: OutputMode::default(),
+            output_file: None,
+            insertion: Insertion::default(),
+            typing_backend: TypingBackend::default(),
+            placement: vec![
+                Placement::BelowCaret,
+                Placement::FocusedControl,
+                Placement::MousePointer,
+                Placement::ScreenCenter,
+            ],
+            rules: Vec::new(),
+            image_dir: PathBuf::from(crate::emoji::DEFAULT_IMAGE_DIR),
+            features: Features::default(),
+        }
+    }
+}
+
+impl Default for Features {
+    fn default() -> Self {
+        Self {
+            caret: true,
+            back_click: true,
+            tray_icon: true,
+            key_redir: true,
+        }
+    }
+}
+
+impl Settings {
+    /// Loads the settings from the user's config directory.
+    /// A missing file results in the default settings.
+    pub fn load() -> Result<Self, SettingsError> {
+        let Some(path) = Self::path() else {
+            return Ok(Self::default());
+        };
+        match fs::read_to_string(path) {
+            Ok(content) => Self::parse(&content),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Returns the path of the settings file.
+    pub fn path() -> Option<PathBuf> {
+        crate::paths::config_file(SETTINGS_FILE)
+    }
+
+    /// Parses and validates the content of a settings file.
+    pub fn parse(content: &str) -> Result<Self, SettingsError> {
+        let settings: Self = toml::from_str(content)?;
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    fn validate(&self) -> Result<(), SettingsError> {
+        if let Some(locale) = &self.locale {
+            if locale.is_empty()
+                || !locale
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "-_.@".contains(c))
+            {
+                return Err(SettingsError::Invalid {
+                    key: "locale",
+                    reason: format!("{:?} is not a locale (e.g. \"fr\" or \"fr-CH\")", locale),
+                });
+            }
+        }
+        for (idx, binding) in self.shortcuts.iter().enumerate() {
+            if self.shortcuts[..idx].iter().any(|b| b.keys == binding.keys) {
+                return Err(SettingsError::Invalid {
+                    key: "shortcuts",
+                    reason: format!("{} is bound more than once", binding.keys),
+                });
+            }
+        }
+        for rule in &self.rules {
+            if rule.process.is_none() && rule.class.is_none() {
+                return Err(SettingsError::Invalid {
+                    key: "rules",
+                    reason: "a rule must match a process or a class".to_owned(),
+                });
+            }
+        }
+        let outputs_to_file = std::iter::once(Some(self.output))
+            .chain(self.rules.iter().map(|rule| rule.output))
+            .any(|output| output == Some(OutputMode::File));
+        if outputs_to_file && self.output_file.is_none() {
+            return Err(SettingsError::Invalid {
+                key: "output_file",
+                reason: "it must be set to output to a file".to_owned(),
+            });
+        }
+        if !POLL_INTERVAL_RANGE.contains(&self.poll_interval_ms) {
+            return Err(out_of_range(
+                "poll_interval_ms",
+                self.poll_interval_ms,
+                &POLL_INTERVAL_RANGE,
+            ));
+        }
+        for (key, size) in [
+            ("button_width", self.button_width),
+            ("button_height", self.button_height),
+        ] {
+            if !BUTTON_SIZE_RANGE.contains(&size) {
+                return Err(out_of_range(key, size, &BUTTON_SIZE_RANGE));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Detects the changes of the settings file, by its modification time.
+pub struct SettingsWatcher {
+    path: Option<PathBuf>,
+    modified: Option<SystemTime>,
+}
+
+impl SettingsWatcher {
+    /// Watches the settings file, as it is now.
+    pub fn new() -> Self {
+        let path = Settings::path();
+        let modified = path.as_deref().and_then(modified);
+        Self { path, modified }
+    }
+
+    /// Returns the settings loaded again if the file changed since the last call,
+    /// or if a reload was requested.
+    pub fn poll(&mut self) -> Option<Result<Settings, SettingsError>> {
+        let modified = self.path.as_deref().and_then(modified);
+        let requested = RELOAD_REQUESTED.swap(false, Ordering::Relaxed);
+        if modified == self.modified && !requested {
+            return None;
+        }
+        self.modified = modified;
+        Some(Settings::load())
+    }
+}
+
+/// Makes the watcher reload the settings on its next check.
+pub fn request_reload() {
+    RELOAD_REQUESTED.store(true, Ordering::Relaxed);
+}
+
+/// Returns the modification time of the file, if it exists.
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn out_of_range<T: std::fmt::Display>(
+    key: &'static str,
+    value: T,
+    range: &RangeInclusive<T>,
+) -> SettingsError {
+    SettingsError::Invalid {
+        key,
+        reason: format!(
+            "{} is not between {} and {}",
+            value,
+            range.start(),
+            range.end()
+        ),
+    }
+}
+
+/// Reads a single value as a list of one value.
+fn one_or_many<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany<T> {
+        One(T),
+        Many(Vec<T>),
+    }
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(value) => vec![value],
+        OneOrMany::Many(values) => values,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the key of the setting rejected when parsing the content.
+    fn invalid_key(content: &str) -> &'static str {
+        match Settings::parse(content) {
+            Err(SettingsError::Invalid { key, .. }) => key,
+            other => panic!("{content:?} was not rejected as invalid: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn reads_the_defaults_from_an_empty_file() {
+        assert_eq!(Settings::parse("").unwrap(), Settings::default());
+    }
+
+    #[test]
+    fn accepts_the_default_values_when_written() {
+        let content = r#"
+            poll_interval_ms = 100
+            button_width = 50.0
+            button_height = 40.0
+            theme = "system"
+            output = "auto"
+            insertion = "type"
+            typing_backend = "auto"
+            placement = ["below-caret", "focused-control", "mouse-pointer", "screen-center"]
+            image_dir = "./emojis/twemoji/assets/svg"
+
+            [[shortcuts]]
+            keys = "Win+."
+
+            [features]
+            caret = true
+            back_click = true
+            tray_icon = true
+            key_redir = true
+        "#;
+        assert_eq!(Settings::parse(content).unwrap(), Settings::default());
+    }
+
+    #[test]
+    fn rejects_a_locale_with_other_characters() {
+        assert_eq!(invalid_key("locale = \"\""), "locale");
+        assert_eq!(invalid_key("locale = \"fr CH\""), "locale");
+        assert_eq!(invalid_key("locale = \"fr/CH\""), "locale");
+        assert!(Settings::parse("locale = \"fr_CH.UTF-8\"").is_ok());
+        assert!(Settings::parse("locale = \"sr@latin\"").is_ok());
+    }
+
+    #[test]
+    fn rejects_a_shortcut_bound_twice() {
+        let content = r#"
+            [[shortcuts]]
+            keys = "Ctrl+Alt+E"
+
+            [[shortcuts]]
+            keys = "Alt+Ctrl+E"
+            group = "favorites"
+        "#;
+        assert_eq!(invalid_key(content), "shortcuts");
+    }
+
+    #[test]
+    fn rejects_a_rule_matching_any_window() {
+        assert_eq!(invalid_key("[[rules]]\noutput = \"clipboard\""), "rules");
+        assert!(Settings::parse("[[rules]]\nclass = \"Console\"\noutput = \"clipboard\"").is_ok());
+    }
+
+    #[test]
+    fn rejects_the_file_output_without_a_file() {
+        assert_eq!(invalid_key("output = \"file\""), "output_file");
+        let content = "[[rules]]\nprocess = \"slack\"\noutput = \"file\"";
+        assert_eq!(invalid_key(content), "output_file");
+        assert!(Settings::parse("output = \"file\"\noutput_file = \"emojis.txt\"").is_ok());
+    }
+
+    #[test]
+    fn rejects_a_poll_interval_out_of_range() {
+        assert_eq!(invalid_key("poll_interval_ms = 9"), "poll_interval_ms");
+        assert_eq!(invalid_key("poll_interval_ms = 1001"), "poll_interval_ms");
+        assert!(Settings::parse("poll_interval_ms = 10").is_ok());
+        assert!(Settings::parse("poll_interval_ms = 1000").is_ok());
+    }
+
+    #[test]
+    fn rejects_a_button_size_out_of_range() {
+        assert_eq!(invalid_key("button_width = 15.9"), "button_width");
+        assert_eq!(invalid_key("button_height = 200.5"), "button_height");
+        assert!(Settings::parse("button_width = 16.0\nbutton_height = 200.0").is_ok());
+    }
+
+    #[test]
+    fn rejects_the_unknown_keys() {
+        assert!(matches!(
+            Settings::parse("poll_interval = 100"),
+            Err(SettingsError::Parse(_))
+        ));
+        assert!(matches!(
+            Settings::parse("[features]\ncaret = true\nsound = true"),
+            Err(SettingsError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn reads_one_placement_or_a_list() {
+        let settings = Settings::parse("placement = \"mouse-pointer\"").unwrap();
+        assert_eq!(settings.placement, [Placement::MousePointer]);
+
+        let settings = Settings::parse("placement = [\"above-caret\", \"last-position\"]").unwrap();
+        assert_eq!(
+            settings.placement,
+            [Placement::AboveCaret, Placement::LastPosition]
+        );
+
+        let settings = Settings::parse("").unwrap();
+        assert_eq!(settings.placement, Settings::default().placement);
+
+        assert!(Settings::parse("placement = \"nowhere\"").is_err());
+        assert!(Settings::parse("placement = [\"mouse-pointer\", 1]").is_err());
+    }
+}
@@ -0,0 +1,445 @@
+use std::{fmt, str::FromStr, sync::RwLock};
+
+use serde::Deserialize;
+
+/// The modifier keys held with the key of a shortcut.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    /// The Windows (or Super, or Command) key.
+    pub logo: bool,
+}
+
+impl Modifiers {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// A key of a shortcut, whatever the platform.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Key {
+    /// A letter, a digit or a punctuation mark, as typed without Shift
+    /// on a US keyboard. Letters are lowercase.
+    Char(char),
+    /// A function key, from F1 to F24.
+    F(u8),
+    Space,
+    Enter,
+    Tab,
+    Escape,
+    Backspace,
+    Insert,
+    Delete,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+/// Named keys, as written in the settings (case insensitive.)
+const KEY_NAMES: &[(&str, Key)] = &[
+    ("space", Key::Space),
+    ("enter", Key::Enter),
+    ("return", Key::Enter),
+    ("tab", Key::Tab),
+    ("esc", Key::Escape),
+    ("escape", Key::Escape),
+    ("backspace", Key::Backspace),
+    ("insert", Key::Insert),
+    ("delete", Key::Delete),
+    ("home", Key::Home),
+    ("end", Key::End),
+    ("pageup", Key::PageUp),
+    ("pagedown", Key::PageDown),
+    ("up", Key::Up),
+    ("down", Key::Down),
+    ("left", Key::Left),
+    ("right", Key::Right),
+    ("period", Key::Char('.')),
+    ("comma", Key::Char(',')),
+    ("equal", Key::Char('=')),
+    ("minus", Key::Char('-')),
+    ("semicolon", Key::Char(';')),
+    ("slash", Key::Char('/')),
+];
+
+/// Punctuation marks of a US keyboard that can be used as keys.
+const PUNCTUATION: &str = ".,-=;/\\'`[]";
+
+impl Key {
+    fn parse(name: &str) -> Option<Self> {
+        let lowercase = name.to_lowercase();
+        if let Some((_, key)) = KEY_NAMES.iter().find(|(n, _)| *n == lowercase) {
+            return Some(*key);
+        }
+        let mut chars = lowercase.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) if c.is_ascii_alphanumeric() || PUNCTUATION.contains(c) => {
+                Some(Key::Char(c))
+            }
+            (Some('f'), Some(_)) => lowercase[1..]
+                .parse()
+                .ok()
+                .filter(|n| (1..=24).contains(n))
+                .map(Key::F),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Key::Char(c) => write!(f, "{}", c.to_ascii_uppercase()),
+            Key::F(n) => write!(f, "F{}", n),
+            key => write!(f, "{:?}", key),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum AcceleratorError {
+    #[error("The shortcut is empty")]
+    Empty,
+    #[error("Unknown modifier `{0}` (expected Ctrl, Alt, Shift or Win)")]
+    UnknownModifier(String),
+    #[error("Unknown key `{0}`")]
+    UnknownKey(String),
+    #[error("A shortcut without modifiers must use a function key, not `{0}`")]
+    MissingModifier(String),
+}
+
+/// A key combination, e.g. "Ctrl+Alt+E" or "Win+.".
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Accelerator {
+    pub modifiers: Modifiers,
+    pub key: Key,
+}
+
+impl Accelerator {
+    /// Whether the pressed key, with the held modifiers, triggers this accelerator.
+    /// The modifiers must be exactly the same, so that "Ctrl+E" is not triggered by "Ctrl+Shift+E".
+    pub fn matches(&self, modifiers: Modifiers, key: Key) -> bool {
+        self.modifiers == modifiers && self.key == key
+    }
+}
+
+impl FromStr for Accelerator {
+    type Err = AcceleratorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts: Vec<&str> = s.split('+').map(str::trim).collect();
+        let key = parts
+            .pop()
+            .filter(|k| !k.is_empty())
+            .ok_or(AcceleratorError::Empty)?;
+
+        let mut modifiers = Modifiers::default();
+        for modifier in parts {
+            let flag = match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => &mut modifiers.ctrl,
+                "alt" | "option" => &mut modifiers.alt,
+                "shift" => &mut modifiers.shift,
+                "win" | "super" | "meta" | "cmd" | "command" | "logo" => &mut modifiers.logo,
+                _ => return Err(AcceleratorError::UnknownModifier(modifier.to_owned())),
+            };
+            *flag = true;
+        }
+
+        let parsed_key =
+            Key::parse(key).ok_or_else(|| AcceleratorError::UnknownKey(key.to_owned()))?;
+        if modifiers.is_empty() && !matches!(parsed_key, Key::F(_)) {
+            return Err(AcceleratorError::MissingModifier(key.to_owned()));
+        }
+        Ok(Self {
+            modifiers,
+            key: parsed_key,
+        })
+    }
+}
+
+impl TryFrom<String> for Accelerator {
+    type Error = AcceleratorError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl fmt::Display for Accelerator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Modifiers {
+            ctrl,
+            alt,
+            shift,
+            logo,
+        } = self.modifiers;
+        for (held, name) in [
+            (ctrl, "Ctrl"),
+            (alt, "Alt"),
+            (shift, "Shift"),
+            (logo, "Win"),
+        ] {
+            if held {
+                write!(f, "{}+", name)?;
+            }
+        }
+        write!(f, "{}", self.key)
+    }
+}
+
+/// A group the picker can be opened into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum OpenGroup {
+    Favorites,
+    RecentlyUsed,
+    SmileysAndEmotion,
+    PeopleAndBody,
+    AnimalsAndNature,
+    FoodAndDrink,
+    TravelAndPlaces,
+    Activities,
+    Objects,
+    Symbols,
+    Flags,
+}
+
+/// How the picker opens when a shortcut is pressed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OpenMode {
+    /// Shows the groups as they were left.
+    #[default]
+    Default,
+    /// Scrolls to the given group.
+    Group(OpenGroup),
+}
+
+impl From<Option<OpenGroup>> for OpenMode {
+    fn from(group: Option<OpenGroup>) -> Self {
+        group.map_or(OpenMode::Default, OpenMode::Group)
+    }
+}
+
+/// A shortcut of the settings, and how it opens the picker.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Binding {
+    pub keys: Accelerator,
+    /// The group to open into, if any.
+    #[serde(default)]
+    pub group: Option<OpenGroup>,
+}
+
+impl Binding {
+    pub fn mode(&self) -> OpenMode {
+        self.group.into()
+    }
+}
+
+/// The bindings currently in use, shared with the platform specific notifiers.
+static BINDINGS: RwLock<Vec<Binding>> = RwLock::new(Vec::new());
+
+pub fn set_bindings(bindings: Vec<Binding>) {
+    *BINDINGS.write().unwrap() = bindings;
+}
+
+pub fn bindings() -> Vec<Binding> {
+    BINDINGS.read().unwrap().clone()
+}
+
+/// Serializes the tests that change the bindings in use.
+#[cfg(test)]
+pub static TEST_BINDINGS_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+/// Returns how to open the picker if the pressed key, with the held modifiers,
+/// is one of the bindings in use.
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))] // Used by the Windows hook
+pub fn find_current(modifiers: Modifiers, key: Key) -> Option<OpenMode> {
+    find(&BINDINGS.read().unwrap(), modifiers, key)
+}
+
+/// Returns how to open the picker if the pressed key, with the held modifiers,
+/// is one of the bindings.
+pub fn find(bindings: &[Binding], modifiers: Modifiers, key: Key) -> Option<OpenMode> {
+    bindings
+        .iter()
+        .find(|binding| binding.keys.matches(modifiers, key))
+        .map(Binding::mode)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::Settings;
+
+    fn modifiers(ctrl: bool, alt: bool, shift: bool, logo: bool) -> Modifiers {
+        Modifiers {
+            ctrl,
+            alt,
+            shift,
+            logo,
+        }
+    }
+
+    fn parse(s: &str) -> Result<Accelerator, AcceleratorError> {
+        s.parse()
+    }
+
+    #[test]
+    fn parses_the_modifiers() {
+        let accelerator = parse("Ctrl+Alt+Shift+Win+E").unwrap();
+        assert_eq!(accelerator.modifiers, modifiers(true, true, true, true));
+        assert_eq!(accelerator.key, Key::Char('e'));
+
+        for (s, expected) in [
+            ("Control+E", modifiers(true, false, false, false)),
+            ("Option+E", modifiers(false, true, false, false)),
+            ("Super+E", modifiers(false, false, false, true)),
+            ("Cmd+E", modifiers(false, false, false, true)),
+            ("Meta+E", modifiers(false, false, false, true)),
+        ] {
+            assert_eq!(parse(s).unwrap().modifiers, expected, "{s}");
+        }
+    }
+
+    #[test]
+    fn parses_ignoring_case_and_spaces() {
+        let expected = parse("Ctrl+Shift+E").unwrap();
+        assert_eq!(parse("ctrl+shift+e").unwrap(), expected);
+        assert_eq!(parse("CTRL + SHIFT + E").unwrap(), expected);
+        assert_eq!(parse("win+PageUp").unwrap().key, Key::PageUp);
+    }
+
+    #[test]
+    fn parses_the_named_keys() {
+        assert_eq!(parse("Win+.").unwrap().key, Key::Char('.'));
+        assert_eq!(parse("Win+Period").unwrap().key, Key::Char('.'));
+        assert_eq!(parse("Ctrl+Return").unwrap().key, Key::Enter);
+        assert_eq!(parse("Ctrl+Esc").unwrap().key, Key::Escape);
+        assert_eq!(parse("Alt+Space").unwrap().key, Key::Space);
+        assert_eq!(parse("Ctrl+7").unwrap().key, Key::Char('7'));
+        assert_eq!(parse("F12").unwrap().key, Key::F(12));
+        assert_eq!(parse("Shift+F24").unwrap().key, Key::F(24));
+    }
+
+    #[test]
+    fn rejects_the_unknown_keys_and_modifiers() {
+        assert!(matches!(parse(""), Err(AcceleratorError::Empty)));
+        assert!(matches!(parse("Ctrl+"), Err(AcceleratorError::Empty)));
+        assert!(matches!(
+            parse("Hyper+E"),
+            Err(AcceleratorError::UnknownModifier(m)) if m == "Hyper"
+        ));
+        for key in ["F0", "F25", "Fx", "Enterr", "é", "Ctrl"] {
+            let parsed = parse(&format!("Ctrl+{key}"));
+            assert!(
+                matches!(parsed, Err(AcceleratorError::UnknownKey(k)) if k == key),
+                "{key}"
+            );
+        }
+    }
+
+    #[test]
+    fn requires_a_modifier_unless_a_function_key() {
+        assert!(matches!(
+            parse("E"),
+            Err(AcceleratorError::MissingModifier(k)) if k == "E"
+        ));
+        assert!(matches!(
+            parse("Space"),
+            Err(AcceleratorError::MissingModifier(_))
+        ));
+        assert!(parse("F1").unwrap().modifiers.is_empty());
+    }
+
+    #[test]
+    fn displays_as_parsed() {
+        for s in ["Ctrl+Alt+Shift+Win+E", "Win+.", "F5", "Ctrl+PageDown"] {
+            assert_eq!(parse(s).unwrap().to_string(), s);
+        }
+        assert_eq!(parse("shift+ctrl+e").unwrap().to_string(), "Ctrl+Shift+E");
+    }
+
+    #[test]
+    fn matches_the_exact_modifiers() {
+        let accelerator = parse("Ctrl+E").unwrap();
+        let ctrl = modifiers(true, false, false, false);
+        assert!(accelerator.matches(ctrl, Key::Char('e')));
+        assert!(!accelerator.matches(ctrl, Key::Char('f')));
+        assert!(!accelerator.matches(modifiers(true, false, true, false), Key::Char('e')));
+        assert!(!accelerator.matches(modifiers(true, true, false, false), Key::Char('e')));
+        assert!(!accelerator.matches(Modifiers::default(), Key::Char('e')));
+    }
+
+    #[test]
+    fn finds_the_binding_and_its_mode() {
+        let bindings = [
+            Binding {
+                keys: parse("Win+.").unwrap(),
+                group: None,
+            },
+            Binding {
+                keys: parse("Win+Shift+.").unwrap(),
+                group: Some(OpenGroup::Favorites),
+            },
+        ];
+        let logo = modifiers(false, false, false, true);
+        assert_eq!(
+            find(&bindings, logo, Key::Char('.')),
+            Some(OpenMode::Default)
+        );
+        assert_eq!(
+            find(
+                &bindings,
+                modifiers(false, false, true, true),
+                Key::Char('.')
+            ),
+            Some(OpenMode::Group(OpenGroup::Favorites))
+        );
+        assert_eq!(find(&bindings, logo, Key::Char(',')), None);
+        assert_eq!(find(&[], logo, Key::Char('.')), None);
+    }
+
+    #[test]
+    fn rejects_the_duplicate_bindings() {
+        let settings = Settings::parse(
+            r#"
+            [[shortcuts]]
+            keys = "Ctrl+Alt+E"
+
+            [[shortcuts]]
+            keys = "alt + control + e"
+            group = "favorites"
+            "#,
+        );
+        assert!(settings.is_err());
+
+        let settings = Settings::parse(
+            r#"
+            [[shortcuts]]
+            keys = "Ctrl+Alt+E"
+
+            [[shortcuts]]
+            keys = "Ctrl+Alt+Shift+E"
+            group = "favorites"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(settings.shortcuts.len(), 2);
+    }
+
+    #[test]
+    fn rejects_the_invalid_bindings() {
+        assert!(Settings::parse("[[shortcuts]]\nkeys = \"Ctrl+Nope\"").is_err());
+        assert!(Settings::parse("[[shortcuts]]\nkeys = \"Win+.\"\ngroup = \"nope\"").is_err());
+    }
+}
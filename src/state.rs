@@ -0,0 +1,48 @@
+use std::{fs, io, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// Name of the file, in the user's data directory, where the state of the picker is stored.
+const STATE_FILE: &str = "state.toml";
+
+/// The state of the picker that is restored on the next run.
+/// Unlike the settings, it is written by the picker itself.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct State {
+    /// Index of the selected skin tone, 0 being the default (yellow) one.
+    pub skin_tone: i32,
+    /// Last logical size of the window, if it was ever closed.
+    pub window_size: Option<(f32, f32)>,
+    /// Last physical position of the window, if it was ever closed.
+    pub window_position: Option<(i32, i32)>,
+    /// Index of the group that was displayed at the top of the list.
+    pub group: i32,
+}
+
+impl State {
+    /// Loads the state from the user's data directory.
+    /// A missing or invalid file results in the default state.
+    pub fn load() -> Self {
+        Self::path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|content| toml::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Writes the state to the user's data directory.
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = Self::path() else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let content = toml::to_string(self).map_err(io::Error::other)?;
+        fs::write(path, content)
+    }
+
+    fn path() -> Option<PathBuf> {
+        crate::paths::data_file(STATE_FILE)
+    }
+}
@@ -0,0 +1,99 @@
+//! Sends keys to the focused window on Linux, where each display server
+//! has its own way of doing it:
+//! - on X11, the XTest extension presses keycodes, mapped for a moment to
+//!   the characters of the text, which are on no keyboard;
+//! - on Wayland, the virtual-keyboard protocol (wlroots-based compositors,
+//!   KDE) presses the keys of a keymap made for the text;
+//! - anywhere, a uinput device of the kernel types the code points after
+//!   Ctrl+Shift+U, which GTK and IBus understand. It presses the keys where
+//!   QWERTY has the hexadecimal digits, so it only works with such layouts
+//!   (AZERTY types `&é"'…` instead of digits) and must be chosen in the settings.
+//!
+//! The backend is chosen from the display server, unless the settings force one.
+
+mod uinput;
+mod virtual_keyboard;
+mod xtest;
+
+use std::{io, sync::RwLock};
+
+use crate::settings::TypingBackend;
+
+static BACKEND: RwLock<TypingBackend> = RwLock::new(TypingBackend::Auto);
+
+#[derive(Debug, thiserror::Error)]
+pub enum TypingError {
+    #[error("Failed to connect to the display server. Reason: {0}")]
+    Connect(String),
+    #[error("The display server does not support the {0} protocol.")]
+    MissingProtocol(&'static str),
+    #[error("There is no free keycode to type the text with.")]
+    NoKeycode,
+    #[error("Failed to open /dev/uinput. Reason: {0}")]
+    Uinput(io::Error),
+    #[error("Failed to send the keys. Reason: {0}")]
+    Io(#[from] io::Error),
+    #[error("Failed to send the keys through XTest. Reason: {0}")]
+    X11(#[from] x11rb::errors::ReplyOrIdError),
+    #[error("Failed to send the keys through the virtual keyboard. Reason: {0}")]
+    Wayland(#[from] wayland_client::DispatchError),
+}
+
+impl TypingError {
+    /// Whether the backend cannot be used on this desktop, so that another one is tried.
+    fn is_unavailable(&self) -> bool {
+        matches!(
+            self,
+            TypingError::Connect(_) | TypingError::MissingProtocol(_)
+        )
+    }
+}
+
+/// What is sent to the focused window.
+#[derive(Debug, Clone, Copy)]
+enum Keys<'a> {
+    Text(&'a str),
+    /// Ctrl+V.
+    Paste,
+}
+
+type Backend = fn(Keys) -> Result<(), TypingError>;
+
+pub fn set_backend(backend: TypingBackend) {
+    *BACKEND.write().unwrap() = backend;
+}
+
+/// Types the text in the focused window.
+pub fn type_text(text: &str) -> Result<(), TypingError> {
+    send(Keys::Text(text))
+}
+
+/// Presses Ctrl+V in the focused window.
+pub fn paste() -> Result<(), TypingError> {
+    send(Keys::Paste)
+}
+
+/// Sends the keys with the first backend available.
+fn send(keys: Keys) -> Result<(), TypingError> {
+    let mut backends = backends().into_iter().peekable();
+    loop {
+        let backend = backends.next().expect("There is always a backend");
+        match backend(keys) {
+            Err(e) if e.is_unavailable() && backends.peek().is_some() => {}
+            result => return result,
+        }
+    }
+}
+
+/// Returns the backends to try, in order.
+/// XTest only reaches the X11 windows, so it is not tried on Wayland.
+fn backends() -> Vec<Backend> {
+    let has_wayland = std::env::var_os("WAYLAND_DISPLAY").is_some();
+    match *BACKEND.read().unwrap() {
+        TypingBackend::Auto if has_wayland => vec![virtual_keyboard::send],
+        TypingBackend::Auto => vec![xtest::send],
+        TypingBackend::Xtest => vec![xtest::send],
+        TypingBackend::VirtualKeyboard => vec![virtual_keyboard::send],
+        TypingBackend::Uinput => vec![uinput::send],
+    }
+}
@@ -0,0 +1,175 @@
+//! Typing through a keyboard device made with uinput, which works under any
+//! display server but needs write access to `/dev/uinput` (e.g. a udev rule
+//! giving it to the `input` group).
+//!
+//! The device has the layout of the user: the characters are typed as their
+//! code point after Ctrl+Shift+U, which GTK and IBus understand, on the keys
+//! where QWERTY has the hexadecimal digits. With another layout, these keys
+//! type other characters (e.g. `&é"'` and `q` on AZERTY), so this backend
+//! is never chosen automatically.
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    os::fd::AsRawFd,
+    time::Duration,
+};
+
+use super::{Keys, TypingError};
+
+const UINPUT: &str = "/dev/uinput";
+
+const UI_SET_EVBIT: libc::c_ulong = 0x4004_5564;
+const UI_SET_KEYBIT: libc::c_ulong = 0x4004_5565;
+const UI_DEV_SETUP: libc::c_ulong = 0x405c_5503;
+const UI_DEV_CREATE: libc::c_ulong = 0x5501;
+const UI_DEV_DESTROY: libc::c_ulong = 0x5502;
+
+const EV_SYN: u16 = 0x00;
+const EV_KEY: u16 = 0x01;
+const SYN_REPORT: u16 = 0;
+const BUS_VIRTUAL: u16 = 0x06;
+
+const KEY_LEFTCTRL: u16 = 29;
+const KEY_LEFTSHIFT: u16 = 42;
+const KEY_U: u16 = 22;
+const KEY_V: u16 = 47;
+const KEY_SPACE: u16 = 57;
+/// The keys of the hexadecimal digits, from 0 to F.
+const HEX_DIGIT_KEYS: [u16; 16] = [11, 2, 3, 4, 5, 6, 7, 8, 9, 10, 30, 48, 46, 32, 18, 33];
+
+/// How long the display server has to find the new device.
+const CREATE_DELAY: Duration = Duration::from_millis(200);
+/// How long the display server has between two keys, and to read the last
+/// ones before the device is removed.
+const KEY_DELAY: Duration = Duration::from_millis(10);
+
+pub fn send(keys: Keys) -> Result<(), TypingError> {
+    let device = Device::create()?;
+    match keys {
+        Keys::Text(text) => {
+            for c in text.chars() {
+                device.press(&[KEY_LEFTCTRL, KEY_LEFTSHIFT, KEY_U])?;
+                for key in code_point_keys(c) {
+                    device.press(&[key])?;
+                }
+                device.press(&[KEY_SPACE])?;
+            }
+        }
+        Keys::Paste => device.press(&[KEY_LEFTCTRL, KEY_V])?,
+    }
+    Ok(())
+}
+
+/// Returns the keys of the hexadecimal digits of the code point of the character.
+fn code_point_keys(c: char) -> Vec<u16> {
+    format!("{:x}", c as u32)
+        .chars()
+        .map(|digit| HEX_DIGIT_KEYS[digit.to_digit(16).unwrap_or_default() as usize])
+        .collect()
+}
+
+/// A keyboard device, removed when dropped.
+struct Device(File);
+
+impl Device {
+    fn create() -> Result<Self, TypingError> {
+        let file = OpenOptions::new()
+            .write(true)
+            .open(UINPUT)
+            .map_err(TypingError::Uinput)?;
+        let fd = file.as_raw_fd();
+
+        let mut setup: libc::uinput_setup = unsafe { std::mem::zeroed() };
+        setup.id.bustype = BUS_VIRTUAL;
+        for (dst, src) in setup.name.iter_mut().zip(b"emoji-picker") {
+            *dst = *src as libc::c_char;
+        }
+        let keys = [KEY_LEFTCTRL, KEY_LEFTSHIFT, KEY_U, KEY_V, KEY_SPACE]
+            .into_iter()
+            .chain(HEX_DIGIT_KEYS);
+        unsafe {
+            check(libc::ioctl(fd, UI_SET_EVBIT as _, EV_KEY as libc::c_int))?;
+            for key in keys {
+                check(libc::ioctl(fd, UI_SET_KEYBIT as _, key as libc::c_int))?;
+            }
+            check(libc::ioctl(fd, UI_DEV_SETUP as _, &setup))?;
+            check(libc::ioctl(fd, UI_DEV_CREATE as _))?;
+        }
+        std::thread::sleep(CREATE_DELAY);
+        Ok(Self(file))
+    }
+
+    /// Presses the keys in order, then releases them in the reverse order.
+    fn press(&self, keys: &[u16]) -> io::Result<()> {
+        for key in keys {
+            self.emit(EV_KEY, *key, 1)?;
+        }
+        self.emit(EV_SYN, SYN_REPORT, 0)?;
+        for key in keys.iter().rev() {
+            self.emit(EV_KEY, *key, 0)?;
+        }
+        self.emit(EV_SYN, SYN_REPORT, 0)?;
+        std::thread::sleep(KEY_DELAY);
+        Ok(())
+    }
+
+    fn emit(&self, type_: u16, code: u16, value: i32) -> io::Result<()> {
+        let event = libc::input_event {
+            time: libc::timeval {
+                tv_sec: 0,
+                tv_usec: 0,
+            },
+            type_,
+            code,
+            value,
+        };
+        let bytes = unsafe {
+            std::slice::from_raw_parts(
+                &event as *const libc::input_event as *const u8,
+                std::mem::size_of::<libc::input_event>(),
+            )
+        };
+        (&self.0).write_all(bytes)
+    }
+}
+
+impl Drop for Device {
+    fn drop(&mut self) {
+        unsafe {
+            libc::ioctl(self.0.as_raw_fd(), UI_DEV_DESTROY as _);
+        }
+    }
+}
+
+/// Turns the result of an ioctl into the error it set.
+fn check(result: libc::c_int) -> io::Result<()> {
+    if result < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY_1: u16 = 2;
+    const KEY_4: u16 = 5;
+    const KEY_6: u16 = 7;
+    const KEY_9: u16 = 10;
+    const KEY_0: u16 = 11;
+    const KEY_A: u16 = 30;
+    const KEY_D: u16 = 32;
+    const KEY_E: u16 = 18;
+    const KEY_F: u16 = 33;
+
+    #[test]
+    fn types_the_hexadecimal_code_point() {
+        assert_eq!(code_point_keys('a'), [KEY_6, KEY_1]);
+        assert_eq!(code_point_keys('é'), [KEY_E, KEY_9]);
+        assert_eq!(code_point_keys('👍'), [KEY_1, KEY_F, KEY_4, KEY_4, KEY_D]);
+        assert_eq!(code_point_keys('\u{fe0f}'), [KEY_F, KEY_E, KEY_0, KEY_F]);
+        assert_eq!(code_point_keys('\u{ad}'), [KEY_A, KEY_D]);
+    }
+}
@@ -0,0 +1,197 @@
+//! Typing through the virtual-keyboard protocol. The keyboard gets a
+//! keymap made of the characters of the text, one key each.
+
+use std::{fmt::Write as _, fs::File, io::Write as _, os::fd::AsFd, time::Instant};
+
+use rustix::fs::MemfdFlags;
+use wayland_client::{
+    globals::{registry_queue_init, GlobalListContents},
+    protocol::{wl_keyboard::KeymapFormat, wl_registry::WlRegistry, wl_seat::WlSeat},
+    Connection, Dispatch, Proxy, QueueHandle,
+};
+use wayland_protocols_misc::zwp_virtual_keyboard_v1::client::{
+    zwp_virtual_keyboard_manager_v1::ZwpVirtualKeyboardManagerV1,
+    zwp_virtual_keyboard_v1::ZwpVirtualKeyboardV1,
+};
+
+use super::{Keys, TypingError};
+
+/// The mask of the Control modifier.
+const CONTROL: u32 = 1 << 2;
+
+/// The evdev codes of the keys are the XKB ones minus 8.
+const EVDEV_OFFSET: u32 = 8;
+const KEY_PRESSED: u32 = 1;
+const KEY_RELEASED: u32 = 0;
+
+pub fn send(keys: Keys) -> Result<(), TypingError> {
+    let connection =
+        Connection::connect_to_env().map_err(|e| TypingError::Connect(e.to_string()))?;
+    let (globals, mut queue) = registry_queue_init::<State>(&connection)
+        .map_err(|e| TypingError::Connect(e.to_string()))?;
+    let handle = queue.handle();
+    let manager: ZwpVirtualKeyboardManagerV1 = globals
+        .bind(&handle, 1..=1, ())
+        .map_err(|_| TypingError::MissingProtocol("virtual-keyboard"))?;
+    let seat: WlSeat = globals
+        .bind(&handle, 1..=1, ())
+        .map_err(|_| TypingError::MissingProtocol("wl_seat"))?;
+    let keyboard = Keyboard {
+        keyboard: manager.create_virtual_keyboard(&seat, &handle, ()),
+        created: Instant::now(),
+    };
+
+    match keys {
+        Keys::Text(text) => {
+            let mut chars: Vec<char> = text.chars().collect();
+            chars.sort_unstable();
+            chars.dedup();
+            let symbols: Vec<String> = chars.iter().copied().map(symbol).collect();
+            keyboard.set_keymap(&symbols)?;
+            for c in text.chars() {
+                keyboard.press(chars.binary_search(&c).unwrap_or_default());
+            }
+        }
+        Keys::Paste => {
+            keyboard.set_keymap(&["v".to_owned()])?;
+            keyboard.keyboard.modifiers(CONTROL, 0, 0, 0);
+            keyboard.press(0);
+            keyboard.keyboard.modifiers(0, 0, 0, 0);
+        }
+    }
+    keyboard.keyboard.destroy();
+    queue.roundtrip(&mut State)?;
+    Ok(())
+}
+
+struct Keyboard {
+    keyboard: ZwpVirtualKeyboardV1,
+    created: Instant,
+}
+
+impl Keyboard {
+    /// Sends a keymap with a key for each symbol, in order.
+    /// It is read from a file, which must end with a null character.
+    fn set_keymap(&self, symbols: &[String]) -> Result<(), TypingError> {
+        let keymap = keymap(symbols);
+        let fd = rustix::fs::memfd_create("emoji-picker-keymap", MemfdFlags::CLOEXEC)
+            .map_err(std::io::Error::from)?;
+        let mut file = File::from(fd);
+        file.write_all(keymap.as_bytes())?;
+        file.write_all(&[0])?;
+        self.keyboard.keymap(
+            KeymapFormat::XkbV1.into(),
+            file.as_fd(),
+            keymap.len() as u32 + 1,
+        );
+        Ok(())
+    }
+
+    /// Presses then releases the key of the symbol at the index.
+    fn press(&self, index: usize) {
+        let time = self.created.elapsed().as_millis() as u32;
+        let key = index as u32 + 1;
+        self.keyboard.key(time, key, KEY_PRESSED);
+        self.keyboard.key(time, key, KEY_RELEASED);
+    }
+}
+
+/// Returns the name of the keysym of the character, e.g. `U1F44D` for 👍.
+fn symbol(c: char) -> String {
+    format!("U{:04X}", c as u32)
+}
+
+/// Returns an XKB keymap where the key `<Kn>` has the n-th symbol.
+fn keymap(symbols: &[String]) -> String {
+    let mut keycodes = String::new();
+    let mut keys = String::new();
+    for (idx, symbol) in symbols.iter().enumerate() {
+        let keycode = idx as u32 + 1 + EVDEV_OFFSET;
+        let _ = writeln!(keycodes, "        <K{}> = {};", idx + 1, keycode);
+        let _ = writeln!(keys, "        key <K{}> {{ [ {} ] }};", idx + 1, symbol);
+    }
+    format!(
+        "xkb_keymap {{
+    xkb_keycodes \"emoji-picker\" {{
+        minimum = {min};
+        maximum = {max};
+{keycodes}    }};
+    xkb_types \"emoji-picker\" {{ include \"complete\" }};
+    xkb_compat \"emoji-picker\" {{ include \"complete\" }};
+    xkb_symbols \"emoji-picker\" {{
+{keys}    }};
+}};
+",
+        min = EVDEV_OFFSET,
+        max = symbols.len() as u32 + 1 + EVDEV_OFFSET,
+    )
+}
+
+struct State;
+
+macro_rules! ignore_events {
+    ($($interface:ty, $user_data:ty;)*) => {$(
+        impl Dispatch<$interface, $user_data> for State {
+            fn event(
+                _: &mut Self,
+                _: &$interface,
+                _: <$interface as Proxy>::Event,
+                _: &$user_data,
+                _: &Connection,
+                _: &QueueHandle<Self>,
+            ) {
+            }
+        }
+    )*};
+}
+
+ignore_events! {
+    WlRegistry, GlobalListContents;
+    WlSeat, ();
+    ZwpVirtualKeyboardManagerV1, ();
+    ZwpVirtualKeyboardV1, ();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_the_keysyms_after_the_code_points() {
+        assert_eq!(symbol('a'), "U0061");
+        assert_eq!(symbol('é'), "U00E9");
+        assert_eq!(symbol('€'), "U20AC");
+        assert_eq!(symbol('👍'), "U1F44D");
+        assert_eq!(symbol('\u{301}'), "U0301");
+    }
+
+    #[test]
+    fn makes_a_key_for_each_symbol() {
+        let symbols = ["U1F44D".to_owned(), "U1F3FD".to_owned()];
+        assert_eq!(
+            keymap(&symbols),
+            "xkb_keymap {
+    xkb_keycodes \"emoji-picker\" {
+        minimum = 8;
+        maximum = 11;
+        <K1> = 9;
+        <K2> = 10;
+    };
+    xkb_types \"emoji-picker\" { include \"complete\" };
+    xkb_compat \"emoji-picker\" { include \"complete\" };
+    xkb_symbols \"emoji-picker\" {
+        key <K1> { [ U1F44D ] };
+        key <K2> { [ U1F3FD ] };
+    };
+};
+"
+        );
+    }
+
+    #[test]
+    fn makes_a_keymap_without_symbols() {
+        let keymap = keymap(&[]);
+        assert!(keymap.contains("minimum = 8;\n        maximum = 9;\n    };"));
+        assert!(!keymap.contains("key <"));
+    }
+}
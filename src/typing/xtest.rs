@@ -0,0 +1,262 @@
+//! Typing through the XTest extension. The keyboard of the X server only
+//! has the keysyms of its layout: the characters of the text are mapped
+//! to keycodes no key uses, then the mapping is restored.
+
+use std::{collections::HashMap, time::Duration};
+
+use x11rb::{
+    connection::{Connection, RequestConnection},
+    errors::{ConnectionError, ReplyError, ReplyOrIdError},
+    protocol::{
+        xproto::{ConnectionExt as _, Keycode, Keysym, Window, KEY_PRESS_EVENT, KEY_RELEASE_EVENT},
+        xtest::{self, ConnectionExt as _},
+    },
+    rust_connection::RustConnection,
+    wrapper::ConnectionExt as _,
+    CURRENT_TIME, NO_SYMBOL,
+};
+
+use super::{Keys, TypingError};
+
+const XK_CONTROL_L: Keysym = 0xffe3;
+const XK_V: Keysym = 0x0076;
+
+/// How long the applications have to notice a change of the keyboard mapping.
+const MAPPING_DELAY: Duration = Duration::from_millis(50);
+
+impl From<ConnectionError> for TypingError {
+    fn from(e: ConnectionError) -> Self {
+        ReplyOrIdError::from(e).into()
+    }
+}
+
+impl From<ReplyError> for TypingError {
+    fn from(e: ReplyError) -> Self {
+        ReplyOrIdError::from(e).into()
+    }
+}
+
+pub fn send(keys: Keys) -> Result<(), TypingError> {
+    let (connection, screen) =
+        x11rb::connect(None).map_err(|e| TypingError::Connect(e.to_string()))?;
+    if connection
+        .extension_information(xtest::X11_EXTENSION_NAME)?
+        .is_none()
+    {
+        return Err(TypingError::MissingProtocol("XTest"));
+    }
+    let root = connection.setup().roots[screen].root;
+    let mapping = Mapping::get(&connection)?;
+    match keys {
+        Keys::Text(text) => type_text(&connection, root, &mapping, text),
+        Keys::Paste => {
+            let control = mapping.find(XK_CONTROL_L).ok_or(TypingError::NoKeycode)?;
+            let v = mapping.find(XK_V).ok_or(TypingError::NoKeycode)?;
+            press(&connection, root, &[control, v])?;
+            connection.sync()?;
+            Ok(())
+        }
+    }
+}
+
+/// Maps the characters to spare keycodes, presses them, then unmaps them.
+fn type_text(
+    connection: &RustConnection,
+    root: Window,
+    mapping: &Mapping,
+    text: &str,
+) -> Result<(), TypingError> {
+    let mut keysyms: Vec<Keysym> = text.chars().map(keysym).collect();
+    keysyms.sort_unstable();
+    keysyms.dedup();
+    let spare = mapping.spare();
+    if spare.len() < keysyms.len() {
+        return Err(TypingError::NoKeycode);
+    }
+    let keycodes: HashMap<Keysym, Keycode> = keysyms.into_iter().zip(spare).collect();
+
+    for (keysym, keycode) in &keycodes {
+        mapping.set(connection, *keycode, *keysym)?;
+    }
+    connection.sync()?;
+    std::thread::sleep(MAPPING_DELAY);
+
+    let typed = text
+        .chars()
+        .try_for_each(|c| press(connection, root, &[keycodes[&keysym(c)]]));
+    connection.sync()?;
+    std::thread::sleep(MAPPING_DELAY);
+
+    for keycode in keycodes.values() {
+        mapping.set(connection, *keycode, NO_SYMBOL)?;
+    }
+    connection.sync()?;
+    typed
+}
+
+/// Presses the keys in order, then releases them in the reverse order.
+fn press(
+    connection: &RustConnection,
+    root: Window,
+    keycodes: &[Keycode],
+) -> Result<(), TypingError> {
+    let events = keycodes
+        .iter()
+        .map(|keycode| (KEY_PRESS_EVENT, keycode))
+        .chain(
+            keycodes
+                .iter()
+                .rev()
+                .map(|keycode| (KEY_RELEASE_EVENT, keycode)),
+        );
+    for (type_, keycode) in events {
+        connection.xtest_fake_input(type_, *keycode, CURRENT_TIME, root, 0, 0, 0)?;
+    }
+    Ok(())
+}
+
+/// The keysym of a character. The Latin-1 ones have their own, the
+/// others are the code point with a flag.
+fn keysym(c: char) -> Keysym {
+    match c as u32 {
+        code @ (0x20..=0x7e | 0xa0..=0xff) => code,
+        code => 0x0100_0000 + code,
+    }
+}
+
+/// The keysyms of each keycode of the keyboard.
+struct Mapping {
+    min_keycode: Keycode,
+    keysyms_per_keycode: u8,
+    keysyms: Vec<Keysym>,
+}
+
+impl Mapping {
+    fn get(connection: &RustConnection) -> Result<Self, TypingError> {
+        let setup = connection.setup();
+        let min_keycode = setup.min_keycode;
+        let count = setup.max_keycode - min_keycode + 1;
+        let reply = connection
+            .get_keyboard_mapping(min_keycode, count)?
+            .reply()?;
+        Ok(Self {
+            min_keycode,
+            keysyms_per_keycode: reply.keysyms_per_keycode,
+            keysyms: reply.keysyms,
+        })
+    }
+
+    fn keycodes(&self) -> impl Iterator<Item = (Keycode, &[Keysym])> {
+        (self.min_keycode..=Keycode::MAX).zip(
+            self.keysyms
+                .chunks(self.keysyms_per_keycode.max(1) as usize),
+        )
+    }
+
+    /// Returns the keycodes having no keysym, which no key uses.
+    fn spare(&self) -> Vec<Keycode> {
+        self.keycodes()
+            .filter(|(_, keysyms)| keysyms.iter().all(|keysym| *keysym == NO_SYMBOL))
+            .map(|(keycode, _)| keycode)
+            .collect()
+    }
+
+    /// Returns the keycode of the keysym, at any level.
+    fn find(&self, keysym: Keysym) -> Option<Keycode> {
+        self.keycodes()
+            .find(|(_, keysyms)| keysyms.contains(&keysym))
+            .map(|(keycode, _)| keycode)
+    }
+
+    /// Maps the keycode to the keysym at every level.
+    fn set(
+        &self,
+        connection: &RustConnection,
+        keycode: Keycode,
+        keysym: Keysym,
+    ) -> Result<(), TypingError> {
+        let keysyms = vec![keysym; self.keysyms_per_keycode as usize];
+        connection.change_keyboard_mapping(1, keycode, self.keysyms_per_keycode, &keysyms)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keysyms(text: &str) -> Vec<Keysym> {
+        text.chars().map(keysym).collect()
+    }
+
+    /// A keyboard of two keysyms per keycode, from the keycode 8.
+    fn mapping(keys: &[[Keysym; 2]]) -> Mapping {
+        Mapping {
+            min_keycode: 8,
+            keysyms_per_keycode: 2,
+            keysyms: keys.concat(),
+        }
+    }
+
+    #[test]
+    fn keeps_the_latin_1_keysyms() {
+        assert_eq!(keysyms("a Z~"), [0x61, 0x20, 0x5a, 0x7e]);
+        assert_eq!(keysyms("é\u{a0}ÿ"), [0xe9, 0xa0, 0xff]);
+    }
+
+    #[test]
+    fn flags_the_other_code_points() {
+        assert_eq!(keysyms("€"), [0x0100_20ac]);
+        assert_eq!(keysyms("❤"), [0x0100_2764]);
+        assert_eq!(keysyms("👍"), [0x0101_f44d]);
+        // The control characters have no keysym of their own.
+        assert_eq!(keysyms("\u{7f}\u{85}"), [0x0100_007f, 0x0100_0085]);
+    }
+
+    #[test]
+    fn types_a_sequence_character_by_character() {
+        // Thumbs up, medium skin tone.
+        assert_eq!(keysyms("👍🏽"), [0x0101_f44d, 0x0101_f3fd]);
+        // Red heart, with the variation selector.
+        assert_eq!(keysyms("❤️"), [0x0100_2764, 0x0100_fe0f]);
+        // "e" and a combining acute accent.
+        assert_eq!(keysyms("e\u{301}"), [0x65, 0x0100_0301]);
+        // Family: man, woman, girl.
+        assert_eq!(
+            keysyms("👨‍👩‍👧"),
+            [
+                0x0101_f468,
+                0x0100_200d,
+                0x0101_f469,
+                0x0100_200d,
+                0x0101_f467
+            ]
+        );
+    }
+
+    #[test]
+    fn spares_the_keycodes_without_keysyms() {
+        let mapping = mapping(&[
+            [0x61, 0x41],
+            [NO_SYMBOL, NO_SYMBOL],
+            [NO_SYMBOL, 0x42],
+            [NO_SYMBOL, NO_SYMBOL],
+        ]);
+        assert_eq!(mapping.spare(), [9, 11]);
+    }
+
+    #[test]
+    fn finds_a_keysym_at_any_level() {
+        let mapping = mapping(&[[0x61, 0x41], [NO_SYMBOL, NO_SYMBOL], [XK_V, 0x56]]);
+        assert_eq!(mapping.find(0x41), Some(8));
+        assert_eq!(mapping.find(XK_V), Some(10));
+        assert_eq!(mapping.find(XK_CONTROL_L), None);
+    }
+
+    #[test]
+    fn stops_at_the_last_keycode() {
+        let mut mapping = mapping(&[[NO_SYMBOL, NO_SYMBOL]; 3]);
+        mapping.min_keycode = 254;
+        assert_eq!(mapping.spare(), [254, 255]);
+    }
+}
@@ -0,0 +1,194 @@
+//! Information about the window the emoji is inserted in.
+
+/// What is known about a window.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WindowInfo {
+    /// Tells the window apart from the other ones of its application:
+    /// its id on X11, its handle (`HWND`) on Windows.
+    pub id: u64,
+    /// The file name of the process owning the window (e.g. `notepad.exe`).
+    pub process: Option<String>,
+    /// The class of the window (the class of its `WM_CLASS` on X11).
+    pub class: Option<String>,
+}
+
+/// Tells which window has the focus.
+pub trait WindowInfoProvider: Send + Sync {
+    fn foreground(&self) -> Option<WindowInfo>;
+}
+
+/// The windows of the desktop.
+/// The foreground window is unknown on Wayland, which does not tell it.
+pub struct SystemWindows;
+
+impl WindowInfoProvider for SystemWindows {
+    fn foreground(&self) -> Option<WindowInfo> {
+        platform::foreground()
+    }
+}
+
+/// Always tells the same window, to check what happens with a given window.
+#[cfg(test)]
+pub struct FakeWindows(pub Option<WindowInfo>);
+
+#[cfg(test)]
+impl WindowInfoProvider for FakeWindows {
+    fn foreground(&self) -> Option<WindowInfo> {
+        self.0.clone()
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use std::path::Path;
+
+    use windows::{
+        core::PWSTR,
+        Win32::{
+            Foundation::{CloseHandle, BOOL, HWND, MAX_PATH},
+            System::Threading::{
+                OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32,
+                PROCESS_QUERY_LIMITED_INFORMATION,
+            },
+            UI::WindowsAndMessaging::{
+                GetClassNameW, GetForegroundWindow, GetWindowThreadProcessId,
+            },
+        },
+    };
+
+    use super::WindowInfo;
+
+    /// Maximum length of a window class name.
+    const MAX_CLASS_NAME: usize = 256;
+
+    pub fn foreground() -> Option<WindowInfo> {
+        let hwnd = unsafe { GetForegroundWindow() };
+        if hwnd.is_invalid() {
+            return None;
+        }
+        Some(WindowInfo {
+            id: hwnd.0 as usize as u64,
+            process: process(hwnd),
+            class: class(hwnd),
+        })
+    }
+
+    fn process(hwnd: HWND) -> Option<String> {
+        unsafe {
+            let mut pid = 0;
+            GetWindowThreadProcessId(hwnd, Some(&mut pid as *mut _));
+            if pid == 0 {
+                return None;
+            }
+            let process =
+                OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, BOOL::from(false), pid).ok()?;
+            let mut path = [0u16; MAX_PATH as usize];
+            let mut len = path.len() as u32;
+            let result = QueryFullProcessImageNameW(
+                process,
+                PROCESS_NAME_WIN32,
+                PWSTR(path.as_mut_ptr()),
+                &mut len,
+            );
+            let _ = CloseHandle(process);
+            result.ok()?;
+
+            let path = String::from_utf16_lossy(&path[..len as usize]);
+            let name = Path::new(&path).file_name()?;
+            Some(name.to_string_lossy().into_owned())
+        }
+    }
+
+    fn class(hwnd: HWND) -> Option<String> {
+        let mut name = [0u16; MAX_CLASS_NAME];
+        let len = unsafe { GetClassNameW(hwnd, &mut name) };
+        (len > 0).then(|| String::from_utf16_lossy(&name[..len as usize]))
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::path::Path;
+
+    use x11rb::{
+        connection::Connection,
+        protocol::xproto::{Atom, AtomEnum, ConnectionExt as _, Window},
+        rust_connection::RustConnection,
+    };
+
+    use super::WindowInfo;
+
+    x11rb::atom_manager! {
+        Atoms: AtomsCookie {
+            _NET_ACTIVE_WINDOW,
+            _NET_WM_PID,
+        }
+    }
+
+    /// The active window, as told to the X11 clients by the window manager.
+    pub fn foreground() -> Option<WindowInfo> {
+        if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+            return None;
+        }
+        let (connection, screen) = x11rb::connect(None).ok()?;
+        let root = connection.setup().roots[screen].root;
+        let atoms = Atoms::new(&connection).ok()?.reply().ok()?;
+        let window = property32(
+            &connection,
+            root,
+            atoms._NET_ACTIVE_WINDOW,
+            AtomEnum::WINDOW,
+        )?;
+        if window == x11rb::NONE {
+            return None;
+        }
+
+        let pid = property32(&connection, window, atoms._NET_WM_PID, AtomEnum::CARDINAL);
+        Some(WindowInfo {
+            id: u64::from(window),
+            process: pid.and_then(process),
+            class: class(&connection, window),
+        })
+    }
+
+    fn property32(
+        connection: &RustConnection,
+        window: Window,
+        property: Atom,
+        type_: AtomEnum,
+    ) -> Option<u32> {
+        let reply = connection
+            .get_property(false, window, property, type_, 0, 1)
+            .ok()?
+            .reply()
+            .ok()?;
+        let value = reply.value32()?.next();
+        value
+    }
+
+    /// `WM_CLASS` holds the instance name, then the class name.
+    fn class(connection: &RustConnection, window: Window) -> Option<String> {
+        let reply = connection
+            .get_property(false, window, AtomEnum::WM_CLASS, AtomEnum::STRING, 0, 1024)
+            .ok()?
+            .reply()
+            .ok()?;
+        let class = reply.value.split(|b| *b == 0).nth(1)?;
+        Some(String::from_utf8_lossy(class).into_owned())
+    }
+
+    fn process(pid: u32) -> Option<String> {
+        let exe = std::fs::read_link(format!("/proc/{}/exe", pid)).ok()?;
+        let name = Path::new(&exe).file_name()?;
+        Some(name.to_string_lossy().into_owned())
+    }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+mod platform {
+    use super::WindowInfo;
+
+    pub fn foreground() -> Option<WindowInfo> {
+        None
+    }
+}
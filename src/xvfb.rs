@@ -0,0 +1,48 @@
+//! A virtual X server for the tests of the X11 code. These tests are ignored
+//! by default: they run with `cargo test -- --include-ignored`, Xvfb installed.
+
+use std::{
+    process::{Child, Command, Stdio},
+    sync::atomic::{AtomicU32, Ordering},
+    time::{Duration, Instant},
+};
+
+/// How many servers the tests of a process start at most.
+const SERVERS_PER_PROCESS: u32 = 4;
+
+static STARTED: AtomicU32 = AtomicU32::new(0);
+
+/// A virtual X server, stopped when dropped.
+pub struct Xvfb {
+    pub display: String,
+    child: Child,
+}
+
+impl Xvfb {
+    /// Starts Xvfb on a display of its own and waits for it to accept connections.
+    pub fn start() -> Self {
+        let started = STARTED.fetch_add(1, Ordering::Relaxed) % SERVERS_PER_PROCESS;
+        let number = 100 + std::process::id() % 200 * SERVERS_PER_PROCESS + started;
+        let display = format!(":{}", number);
+        let child = Command::new("Xvfb")
+            .args([display.as_str(), "-nolisten", "tcp"])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("Xvfb must be installed to run this test");
+        let xvfb = Self { display, child };
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while x11rb::connect(Some(&xvfb.display)).is_err() {
+            assert!(Instant::now() < deadline, "Xvfb did not start");
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        xvfb
+    }
+}
+
+impl Drop for Xvfb {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}